@@ -0,0 +1,137 @@
+mod github;
+mod stackexchange;
+mod wikipedia;
+
+use anyhow::{Context, Result};
+use headless_chrome::Tab;
+use serde::de::DeserializeOwned;
+use url::Url;
+
+use crate::extract::{ExtractOptions, Match, extraction_script, parse_matches, pick_match_images};
+use crate::selector::Selector;
+
+use self::github::GitHub;
+use self::stackexchange::StackExchange;
+use self::wikipedia::Wikipedia;
+
+/// サイトアダプタの抽出結果
+#[derive(Debug, PartialEq, Eq)]
+pub enum Extracted {
+    /// 変換せずに出力する Markdown。相対 URL は `base_url` を基準に解決する。
+    /// 画像だけ別の基準で解決する場合（ページと生ファイルの URL が異なるサイト）は `image_base_url` を使う。
+    Markdown {
+        text: String,
+        base_url: String,
+        image_base_url: Option<String>,
+    },
+    /// 汎用経路と同じく Markdown に変換する HTML 断片
+    Html(Vec<String>),
+}
+
+/// 特定サイト向けに、汎用のセレクタ抽出の代わりに内容を取り出す処理
+pub trait Adapter: Sync {
+    /// 進捗表示に使う名前
+    fn name(&self) -> &'static str;
+
+    /// この URL を扱うかどうか
+    fn matches(&self, url: &Url) -> bool;
+
+    /// 読み込み済みのページから内容を取り出す。
+    /// HTML 断片には汎用の抽出と同じ `options` の処理を加える。
+    /// 対応できないページなら `Ok(None)` を返し、汎用の抽出に任せる。
+    fn extract(&self, tab: &Tab, url: &Url, options: &ExtractOptions) -> Result<Option<Extracted>>;
+}
+
+/// 登録済みのアダプタ（先に一致したものを使う）
+const ADAPTERS: &[&dyn Adapter] = &[&GitHub, &Wikipedia, &StackExchange];
+
+/// URL を扱うアダプタを探す
+pub fn find_adapter(url: &str) -> Option<(&'static dyn Adapter, Url)> {
+    let url = Url::parse(url).ok()?;
+    ADAPTERS
+        .iter()
+        .find(|adapter| adapter.matches(&url))
+        .map(|adapter| (*adapter, url))
+}
+
+/// JSON 文字列（または null）を返すスクリプトを評価し、結果を解釈する
+fn evaluate_json<T: DeserializeOwned>(
+    tab: &Tab,
+    js: &str,
+    await_promise: bool,
+) -> Result<Option<T>> {
+    let result = tab
+        .evaluate(js, await_promise)
+        .context("Failed to evaluate adapter script")?;
+    match result.value.as_ref().and_then(|v| v.as_str()) {
+        Some(json) => serde_json::from_str(json)
+            .map(Some)
+            .context("Invalid adapter script result"),
+        None => Ok(None),
+    }
+}
+
+/// CSS セレクタに一致した要素を、汎用の抽出と同じ `options` の処理を加えて取り出す
+fn extract_matches(tab: &Tab, selector: &str, options: &ExtractOptions) -> Result<Vec<Match>> {
+    let js = extraction_script(&Selector::Css(selector.to_string()), options);
+    let result = tab
+        .evaluate(&js, false)
+        .context("Failed to evaluate adapter selector")?;
+    let mut matches = parse_matches(result.value.as_ref())?;
+    pick_match_images(&mut matches, options);
+    Ok(matches)
+}
+
+/// ホスト名が `domain` そのもの、またはそのサブドメインかを判定する
+fn host_is(url: &Url, domain: &str) -> bool {
+    url.host_str().is_some_and(|host| {
+        host == domain
+            || host
+                .strip_suffix(domain)
+                .is_some_and(|rest| rest.ends_with('.'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adapter_name(url: &str) -> Option<&'static str> {
+        find_adapter(url).map(|(adapter, _)| adapter.name())
+    }
+
+    // find_adapter のテスト
+
+    #[test]
+    fn find_adapter_by_url() {
+        assert_eq!(
+            adapter_name("https://github.com/owayo/get-md"),
+            Some("github")
+        );
+        assert_eq!(
+            adapter_name("https://en.wikipedia.org/wiki/Markdown"),
+            Some("wikipedia")
+        );
+        assert_eq!(
+            adapter_name("https://stackoverflow.com/questions/1/title"),
+            Some("stackexchange")
+        );
+    }
+
+    #[test]
+    fn find_adapter_none_for_other_sites() {
+        assert_eq!(adapter_name("https://example.com/"), None);
+        assert_eq!(adapter_name("https://github.com/"), None);
+        assert_eq!(adapter_name("not a url"), None);
+    }
+
+    // host_is のテスト
+
+    #[test]
+    fn host_is_domain_or_subdomain() {
+        let url = Url::parse("https://ja.wikipedia.org/wiki/X").unwrap();
+        assert!(host_is(&url, "wikipedia.org"));
+        assert!(host_is(&url, "ja.wikipedia.org"));
+        assert!(!host_is(&url, "pedia.org"));
+    }
+}
@@ -0,0 +1,353 @@
+use anyhow::Result;
+use headless_chrome::Tab;
+use serde::Deserialize;
+use url::Url;
+
+use super::{Adapter, Extracted, evaluate_json};
+use crate::extract::ExtractOptions;
+
+/// 生のソースをそのまま出力するファイルの拡張子
+const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown", "mdx", "mdown", "mkd", "mkdn"];
+
+/// GitHub のリポジトリトップ・ディレクトリ・ファイルのページから、
+/// レンダリング結果ではなく元のソースを取り出すアダプタ
+pub struct GitHub;
+
+/// アダプタが扱う GitHub のページ
+#[derive(Debug, PartialEq, Eq)]
+enum Page {
+    /// リポジトリトップまたはディレクトリの README
+    Readme {
+        owner: String,
+        repo: String,
+        /// `tree/` 以降（`<ref>/<dir>`）
+        tree: Option<String>,
+    },
+    /// `blob/` のファイル
+    File {
+        owner: String,
+        repo: String,
+        /// `blob/` 以降（`<ref>/<path>`）
+        rest: String,
+    },
+}
+
+/// ページ内で取得したソース
+#[derive(Deserialize)]
+struct Source {
+    text: String,
+    base_url: String,
+}
+
+/// リポジトリ以外のトップレベルのパス
+const RESERVED_OWNERS: &[&str] = &[
+    "about",
+    "collections",
+    "explore",
+    "features",
+    "login",
+    "marketplace",
+    "notifications",
+    "orgs",
+    "pricing",
+    "search",
+    "settings",
+    "sponsors",
+    "topics",
+];
+
+fn parse_page(url: &Url) -> Option<Page> {
+    let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+    let (owner, repo) = match segments.as_slice() {
+        [owner, repo, ..] if !RESERVED_OWNERS.contains(owner) => {
+            (owner.to_string(), repo.to_string())
+        }
+        _ => return None,
+    };
+    match &segments[2..] {
+        [] => Some(Page::Readme {
+            owner,
+            repo,
+            tree: None,
+        }),
+        ["tree", rest @ ..] if !rest.is_empty() => Some(Page::Readme {
+            owner,
+            repo,
+            tree: Some(rest.join("/")),
+        }),
+        ["blob", rest @ ..] if rest.len() >= 2 => Some(Page::File {
+            owner,
+            repo,
+            rest: rest.join("/"),
+        }),
+        _ => None,
+    }
+}
+
+/// README を探す REST API の URL の候補。
+///
+/// `tree/` 以降はブランチ名とディレクトリの境界が URL から分からない（`feature/x` のように
+/// ブランチ名が `/` を含みうる）ため、短いブランチ名から順にすべての区切り方を候補にする。
+fn readme_api_urls(owner: &str, repo: &str, tree: Option<&str>) -> Vec<String> {
+    let api = format!("https://api.github.com/repos/{owner}/{repo}/readme");
+    let Some(tree) = tree else {
+        return vec![api];
+    };
+    let segments: Vec<&str> = tree.split('/').filter(|s| !s.is_empty()).collect();
+    (1..=segments.len())
+        .map(|i| {
+            let (git_ref, dir) = segments.split_at(i);
+            let dir: String = dir.iter().map(|s| format!("/{s}")).collect();
+            format!("{api}{dir}?ref={}", git_ref.join("/"))
+        })
+        .collect()
+}
+
+/// README を REST API で探し、その生ファイルを取得するスクリプト（最初に見つかった候補を使う）
+fn readme_script(owner: &str, repo: &str, tree: Option<&str>) -> String {
+    let urls: Vec<String> = readme_api_urls(owner, repo, tree)
+        .iter()
+        .map(|url| crate::escape_js_string(url))
+        .collect();
+    format!(
+        r#"(async () => {{
+            for (const api of [{}]) {{
+                const res = await fetch(api, {{ headers: {{ Accept: 'application/vnd.github+json' }} }});
+                if (!res.ok) continue;
+                const info = await res.json();
+                const raw = await fetch(info.download_url);
+                if (!raw.ok) return null;
+                return JSON.stringify({{ text: await raw.text(), base_url: info.html_url }});
+            }}
+            return null;
+        }})()"#,
+        urls.join(", ")
+    )
+}
+
+/// ファイルの生データをページと同じオリジンから取得するスクリプト。
+///
+/// `/raw/` は raw.githubusercontent.com にリダイレクトされ、そこは
+/// `Access-Control-Allow-Origin: *` を返すため、Cookie は github.com にだけ送る
+/// （`include` ではリダイレクト先の応答がブラウザに拒否される）。
+fn raw_file_script(owner: &str, repo: &str, rest: &str) -> String {
+    let raw = format!("/{owner}/{repo}/raw/{rest}");
+    format!(
+        r#"(async () => {{
+            const res = await fetch({}, {{ credentials: 'same-origin' }});
+            const type = res.headers.get('content-type') || '';
+            if (!res.ok || !type.startsWith('text/')) return null;
+            return JSON.stringify({{ text: await res.text(), base_url: location.href }});
+        }})()"#,
+        crate::escape_js_string(&raw)
+    )
+}
+
+/// `blob/` のページ URL に対応する生ファイルの URL（`raw/`）。
+///
+/// README の相対パスの画像はページ（HTML）ではなく生ファイルとして解決する必要がある。
+fn raw_url(blob_url: &str) -> Option<String> {
+    let mut url = Url::parse(blob_url).ok()?;
+    let segments: Vec<String> = url.path_segments()?.map(String::from).collect();
+    if segments.get(2).map(String::as_str) != Some("blob") {
+        return None;
+    }
+    url.path_segments_mut()
+        .ok()?
+        .clear()
+        .extend(&segments[..2])
+        .push("raw")
+        .extend(&segments[3..]);
+    Some(url.into())
+}
+
+/// Markdown 以外のファイルを、拡張子を言語名にしたフェンス付きコードブロックにする
+fn fence_source(text: &str, language: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    let body = text.strip_suffix('\n').unwrap_or(text);
+    format!("{fence}{language}\n{body}\n{fence}")
+}
+
+/// パスの拡張子（小文字）
+fn extension(path: &str) -> Option<String> {
+    let name = path.rsplit('/').next()?;
+    let (stem, ext) = name.rsplit_once('.')?;
+    (!stem.is_empty()).then(|| ext.to_ascii_lowercase())
+}
+
+impl Adapter for GitHub {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        url.host_str() == Some("github.com") && parse_page(url).is_some()
+    }
+
+    fn extract(
+        &self,
+        tab: &Tab,
+        url: &Url,
+        _options: &ExtractOptions,
+    ) -> Result<Option<Extracted>> {
+        let Some(page) = parse_page(url) else {
+            return Ok(None);
+        };
+        let (js, language) = match &page {
+            Page::Readme { owner, repo, tree } => {
+                (readme_script(owner, repo, tree.as_deref()), None)
+            }
+            Page::File { owner, repo, rest } => {
+                let ext = extension(rest);
+                let language = ext.filter(|e| !MARKDOWN_EXTENSIONS.contains(&e.as_str()));
+                (raw_file_script(owner, repo, rest), language)
+            }
+        };
+        let source: Option<Source> = evaluate_json(tab, &js, true)?;
+        Ok(source.map(|source| Extracted::Markdown {
+            text: match language {
+                Some(language) => fence_source(&source.text, &language),
+                None => source.text,
+            },
+            image_base_url: raw_url(&source.base_url),
+            base_url: source.base_url,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(url: &str) -> Option<Page> {
+        parse_page(&Url::parse(url).unwrap())
+    }
+
+    // parse_page のテスト
+
+    #[test]
+    fn parse_repository_top() {
+        assert_eq!(
+            page("https://github.com/owayo/get-md"),
+            Some(Page::Readme {
+                owner: "owayo".to_string(),
+                repo: "get-md".to_string(),
+                tree: None,
+            })
+        );
+        assert!(page("https://github.com/owayo/get-md/").is_some());
+    }
+
+    #[test]
+    fn parse_tree_page() {
+        assert_eq!(
+            page("https://github.com/o/r/tree/main/docs/guide"),
+            Some(Page::Readme {
+                owner: "o".to_string(),
+                repo: "r".to_string(),
+                tree: Some("main/docs/guide".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_blob_page() {
+        assert_eq!(
+            page("https://github.com/o/r/blob/main/docs/README.md"),
+            Some(Page::File {
+                owner: "o".to_string(),
+                repo: "r".to_string(),
+                rest: "main/docs/README.md".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_other_pages_are_not_handled() {
+        assert_eq!(page("https://github.com/"), None);
+        assert_eq!(page("https://github.com/owayo"), None);
+        assert_eq!(page("https://github.com/o/r/issues/1"), None);
+        assert_eq!(page("https://github.com/o/r/blob/main"), None);
+        assert_eq!(page("https://github.com/settings/profile"), None);
+    }
+
+    // スクリプト生成のテスト
+
+    #[test]
+    fn readme_script_for_repository_top() {
+        let js = readme_script("o", "r", None);
+        assert!(js.contains(r#""https://api.github.com/repos/o/r/readme""#));
+    }
+
+    #[test]
+    fn readme_script_for_tree() {
+        let js = readme_script("o", "r", Some("main/docs"));
+        assert!(js.contains(r#""https://api.github.com/repos/o/r/readme/docs?ref=main""#));
+        let js = readme_script("o", "r", Some("dev"));
+        assert!(js.contains(r#""https://api.github.com/repos/o/r/readme?ref=dev""#));
+    }
+
+    #[test]
+    fn readme_api_urls_try_every_ref_split() {
+        assert_eq!(
+            readme_api_urls("o", "r", Some("feature/x/docs")),
+            vec![
+                "https://api.github.com/repos/o/r/readme/x/docs?ref=feature",
+                "https://api.github.com/repos/o/r/readme/docs?ref=feature/x",
+                "https://api.github.com/repos/o/r/readme?ref=feature/x/docs",
+            ]
+        );
+        assert_eq!(
+            readme_api_urls("o", "r", None),
+            vec!["https://api.github.com/repos/o/r/readme"]
+        );
+    }
+
+    #[test]
+    fn raw_file_script_sends_cookies_only_to_github() {
+        let js = raw_file_script("o", "r", "main/a.md");
+        assert!(js.contains(r#"fetch("/o/r/raw/main/a.md", { credentials: 'same-origin' })"#));
+    }
+
+    // raw_url のテスト
+
+    #[test]
+    fn raw_url_from_blob_page() {
+        assert_eq!(
+            raw_url("https://github.com/o/r/blob/main/docs/README.md").as_deref(),
+            Some("https://github.com/o/r/raw/main/docs/README.md")
+        );
+        // README の相対パスの画像は生ファイルとして解決される
+        let base =
+            Url::parse(&raw_url("https://github.com/o/r/blob/main/README.md").unwrap()).unwrap();
+        assert_eq!(
+            base.join("docs/logo.png").unwrap().as_str(),
+            "https://github.com/o/r/raw/main/docs/logo.png"
+        );
+        assert_eq!(raw_url("https://github.com/o/r/tree/main/docs"), None);
+        assert_eq!(raw_url("not a url"), None);
+    }
+
+    // fence_source / extension のテスト
+
+    #[test]
+    fn fence_source_wraps_code() {
+        assert_eq!(
+            fence_source("fn main() {}\n", "rs"),
+            "```rs\nfn main() {}\n```"
+        );
+    }
+
+    #[test]
+    fn fence_source_extends_fence_over_backticks() {
+        assert_eq!(fence_source("a ```` b", "txt"), "`````txt\na ```` b\n`````");
+    }
+
+    #[test]
+    fn extension_of_path() {
+        assert_eq!(extension("main/src/Main.RS"), Some("rs".to_string()));
+        assert_eq!(extension("main/Makefile"), None);
+        assert_eq!(extension("main/.gitignore"), None);
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::{Result, bail};
+use headless_chrome::Tab;
+use serde::Deserialize;
+use url::Url;
+
+use super::{Adapter, Extracted, evaluate_json, extract_matches, host_is};
+use crate::extract::{ExtractOptions, Match};
+
+/// 質問と回答の見出しを `Post` の JSON 配列として返すスクリプト
+const STACKEXCHANGE_JS: &str = include_str!("../js/stackexchange.js");
+
+/// 質問と回答の本文（スクリプトの `Post` と同じ順に一致する）
+const BODY_SELECTOR: &str = "#question .js-post-body, #answers .answer .js-post-body";
+
+/// 質問または回答
+#[derive(Debug, Deserialize)]
+struct Post {
+    /// 見出しとスコアの HTML
+    head: String,
+    /// 本文があるか
+    body: bool,
+}
+
+/// `*.stackexchange.com` 以外の Stack Exchange ネットワークのサイト
+const SITES: &[&str] = &[
+    "stackoverflow.com",
+    "serverfault.com",
+    "superuser.com",
+    "askubuntu.com",
+    "mathoverflow.net",
+    "stackapps.com",
+    "stackexchange.com",
+];
+
+/// Stack Overflow などの Q&A ページから、質問と回答をスコア付きで取り出すアダプタ
+pub struct StackExchange;
+
+impl Adapter for StackExchange {
+    fn name(&self) -> &'static str {
+        "stackexchange"
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let is_question = url
+            .path_segments()
+            .and_then(|mut segments| {
+                let first = segments.next()?;
+                let id = segments.next()?;
+                Some(
+                    first == "questions"
+                        && !id.is_empty()
+                        && id.bytes().all(|b| b.is_ascii_digit()),
+                )
+            })
+            .unwrap_or(false);
+        is_question && SITES.iter().any(|site| host_is(url, site))
+    }
+
+    fn extract(
+        &self,
+        tab: &Tab,
+        _url: &Url,
+        options: &ExtractOptions,
+    ) -> Result<Option<Extracted>> {
+        let Some(posts) = evaluate_json::<Vec<Post>>(tab, STACKEXCHANGE_JS, false)? else {
+            return Ok(None);
+        };
+        let bodies = extract_matches(tab, BODY_SELECTOR, options)?;
+        join_posts(posts, bodies).map(|fragments| Some(Extracted::Html(fragments)))
+    }
+}
+
+/// 見出しと本文を組にして、投稿ごとの HTML 断片にする
+fn join_posts(posts: Vec<Post>, bodies: Vec<Match>) -> Result<Vec<String>> {
+    let expected = posts.iter().filter(|post| post.body).count();
+    if bodies.len() != expected {
+        bail!("found {} post bodies for {expected} posts", bodies.len());
+    }
+    let mut bodies = bodies.into_iter();
+    Ok(posts
+        .into_iter()
+        .map(|post| match post.body.then(|| bodies.next()).flatten() {
+            Some(body) => post.head + &body.html,
+            None => post.head,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(url: &str) -> bool {
+        StackExchange.matches(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn matches_question_pages() {
+        assert!(matches(
+            "https://stackoverflow.com/questions/11227809/why-is-it-faster"
+        ));
+        assert!(matches("https://ja.stackoverflow.com/questions/1"));
+        assert!(matches("https://unix.stackexchange.com/questions/42/title"));
+        assert!(matches("https://askubuntu.com/questions/7/title"));
+    }
+
+    #[test]
+    fn does_not_match_other_pages() {
+        assert!(!matches("https://stackoverflow.com/questions"));
+        assert!(!matches("https://stackoverflow.com/questions/tagged/rust"));
+        assert!(!matches("https://stackoverflow.com/users/1/name"));
+        assert!(!matches("https://example.com/questions/1"));
+    }
+
+    // join_posts のテスト
+
+    fn post(head: &str, body: bool) -> Post {
+        Post {
+            head: head.to_string(),
+            body,
+        }
+    }
+
+    fn body(html: &str) -> Match {
+        Match {
+            html: html.to_string(),
+            path: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn join_posts_pairs_heads_with_bodies() {
+        let posts = vec![
+            post("<h1>Q</h1>", true),
+            post("<h2>A1</h2>", false),
+            post("<h2>A2</h2>", true),
+        ];
+        let bodies = vec![body("<div>q</div>"), body("<div>a2</div>")];
+        assert_eq!(
+            join_posts(posts, bodies).unwrap(),
+            vec![
+                "<h1>Q</h1><div>q</div>",
+                "<h2>A1</h2>",
+                "<h2>A2</h2><div>a2</div>"
+            ]
+        );
+    }
+
+    #[test]
+    fn join_posts_rejects_unmatched_bodies() {
+        assert!(join_posts(vec![post("<h1>Q</h1>", true)], Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use headless_chrome::Tab;
+use url::Url;
+
+use super::{Adapter, Extracted, extract_matches, host_is};
+use crate::extract::ExtractOptions;
+
+/// 記事タイトル
+const TITLE_SELECTOR: &str = "#firstHeading";
+
+/// 記事本文
+const CONTENT_SELECTOR: &str = "#mw-content-text > .mw-parser-output";
+
+/// 本文から取り除く編集リンク・情報ボックス・ナビゲーション等
+const NOISE_SELECTORS: &[&str] = &[
+    ".mw-editsection",
+    ".infobox",
+    ".navbox",
+    ".vertical-navbox",
+    ".sidebar",
+    ".ambox",
+    ".metadata",
+    ".noprint",
+    ".mw-empty-elt",
+    "#toc",
+    ".toc",
+    "style",
+];
+
+/// Wikipedia の記事をタイトルと本文だけにして取り出すアダプタ
+pub struct Wikipedia;
+
+impl Adapter for Wikipedia {
+    fn name(&self) -> &'static str {
+        "wikipedia"
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        host_is(url, "wikipedia.org") && url.path().starts_with("/wiki/")
+    }
+
+    fn extract(
+        &self,
+        tab: &Tab,
+        _url: &Url,
+        options: &ExtractOptions,
+    ) -> Result<Option<Extracted>> {
+        let mut options = options.clone();
+        options
+            .exclude
+            .extend(NOISE_SELECTORS.iter().map(ToString::to_string));
+        let mut html = Vec::new();
+        for selector in [TITLE_SELECTOR, CONTENT_SELECTOR] {
+            let matches = extract_matches(tab, selector, &options)?;
+            // 本文が見つからなければ記事ページではない
+            if matches.is_empty() && selector == CONTENT_SELECTOR {
+                return Ok(None);
+            }
+            html.extend(matches.into_iter().map(|m| m.html));
+        }
+        Ok(Some(Extracted::Html(vec![html.join("\n")])))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(url: &str) -> bool {
+        Wikipedia.matches(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn matches_article_pages() {
+        assert!(matches("https://en.wikipedia.org/wiki/Markdown"));
+        assert!(matches("https://ja.m.wikipedia.org/wiki/Markdown"));
+    }
+
+    #[test]
+    fn does_not_match_other_pages() {
+        assert!(!matches(
+            "https://en.wikipedia.org/w/index.php?title=Markdown"
+        ));
+        assert!(!matches("https://www.wikipedia.org/"));
+        assert!(!matches("https://wikipedia.org.example.com/wiki/X"));
+    }
+}
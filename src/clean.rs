@@ -0,0 +1,225 @@
+use url::Url;
+
+/// 既定で取り除くトラッキング用のクエリパラメータ（`*` で終わるものは前方一致）
+pub const DEFAULT_TRACKING_PARAMS: &[&str] = &[
+    "utm_*",
+    "fbclid",
+    "gclid",
+    "dclid",
+    "gbraid",
+    "wbraid",
+    "msclkid",
+    "yclid",
+    "twclid",
+    "igshid",
+    "mc_cid",
+    "mc_eid",
+    "_ga",
+    "_gl",
+    "_hsenc",
+    "_hsmi",
+    "mkt_tok",
+    "jsessionid",
+    "phpsessid",
+    "sessionid",
+];
+
+/// `--clean-urls` による URL の正規化
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UrlCleaner {
+    /// 取り除くパラメータ名（小文字）
+    params: Vec<String>,
+}
+
+impl UrlCleaner {
+    /// 既定のパラメータに `extra` を加える
+    pub fn new(extra: &[String]) -> Self {
+        let mut params: Vec<String> = DEFAULT_TRACKING_PARAMS
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        for param in extra {
+            let param = param.trim().to_ascii_lowercase();
+            if !param.is_empty() && !params.contains(&param) {
+                params.push(param);
+            }
+        }
+        UrlCleaner { params }
+    }
+
+    fn is_tracking(&self, name: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        self.params.iter().any(|p| match p.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == *p,
+        })
+    }
+
+    /// トラッキング用のパラメータ（`;jsessionid=...` などのパスパラメータを含む）を取り除き、
+    /// パーセントエンコーディングを正規化する（既定のポートは `Url` の解析時に省かれる）
+    pub fn clean(&self, mut url: Url) -> Url {
+        if url.cannot_be_a_base() {
+            return url;
+        }
+        let path = self.strip_path_params(url.path());
+        url.set_path(&normalize_percent_encoding(&path));
+
+        if let Some(query) = url.query() {
+            let kept: Vec<&str> = query
+                .split('&')
+                .filter(|pair| {
+                    let name = pair.split('=').next().unwrap_or_default();
+                    !pair.is_empty() && !self.is_tracking(&percent_decode(name))
+                })
+                .collect();
+            let query = normalize_percent_encoding(&kept.join("&"));
+            url.set_query((!query.is_empty()).then_some(query.as_str()));
+        }
+        if let Some(fragment) = url.fragment() {
+            let fragment = normalize_percent_encoding(fragment);
+            url.set_fragment(Some(&fragment));
+        }
+        url
+    }
+
+    /// パスの各セグメントから `;name=value` のトラッキング用パラメータを取り除く
+    fn strip_path_params(&self, path: &str) -> String {
+        path.split('/')
+            .map(|segment| {
+                let mut parts = segment.split(';');
+                let mut out = parts.next().unwrap_or_default().to_string();
+                for param in parts {
+                    let name = param.split('=').next().unwrap_or_default();
+                    if !self.is_tracking(&percent_decode(name)) {
+                        out.push(';');
+                        out.push_str(param);
+                    }
+                }
+                out
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+/// パーセントエンコーディングの正規化（非予約文字はデコードし、16 進は大文字にする）
+fn normalize_percent_encoding(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(byte) = escaped_byte(bytes, i) {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+                out.push(byte as char);
+            } else {
+                out.push_str(&format!("%{byte:02X}"));
+            }
+            i += 3;
+            continue;
+        }
+        let c = s[i..].chars().next().unwrap_or_default();
+        out.push(c);
+        i += c.len_utf8();
+    }
+    out
+}
+
+/// パラメータ名の比較用に `%XX` と `+` をデコードする
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(byte) = escaped_byte(bytes, i) {
+            out.push(byte);
+            i += 3;
+        } else {
+            out.push(if bytes[i] == b'+' { b' ' } else { bytes[i] });
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// `bytes[i..]` が `%XX` ならそのバイト
+fn escaped_byte(bytes: &[u8], i: usize) -> Option<u8> {
+    if bytes.get(i) != Some(&b'%') {
+        return None;
+    }
+    let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+    u8::from_str_radix(hex, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean(url: &str) -> String {
+        UrlCleaner::new(&[])
+            .clean(Url::parse(url).unwrap())
+            .to_string()
+    }
+
+    // UrlCleaner::clean のテスト
+
+    #[test]
+    fn removes_tracking_params() {
+        assert_eq!(
+            clean("https://a.test/p?id=3&utm_source=x&UTM_Medium=y&fbclid=z&gclid=w#top"),
+            "https://a.test/p?id=3#top"
+        );
+        assert_eq!(
+            clean("https://a.test/p?utm_source=x&fbclid=z"),
+            "https://a.test/p"
+        );
+    }
+
+    #[test]
+    fn keeps_other_params_verbatim() {
+        assert_eq!(
+            clean("https://a.test/s?q=a+b&tag=c%2Cd&utm_campaign=1"),
+            "https://a.test/s?q=a+b&tag=c%2Cd"
+        );
+    }
+
+    #[test]
+    fn removes_session_path_params() {
+        assert_eq!(
+            clean("https://a.test/app/page;jsessionid=ABC123;v=2?x=1"),
+            "https://a.test/app/page;v=2?x=1"
+        );
+    }
+
+    #[test]
+    fn drops_default_port() {
+        assert_eq!(clean("https://a.test:443/p"), "https://a.test/p");
+        assert_eq!(clean("http://a.test:8080/p"), "http://a.test:8080/p");
+    }
+
+    #[test]
+    fn normalizes_percent_encoding() {
+        assert_eq!(
+            clean("https://a.test/%7euser/%61b%2fc?q=%7e%2a#s%2d1"),
+            "https://a.test/~user/ab%2Fc?q=~%2A#s-1"
+        );
+    }
+
+    #[test]
+    fn extra_params() {
+        let cleaner = UrlCleaner::new(&["Ref".to_string(), "ab_*".to_string()]);
+        assert_eq!(
+            cleaner
+                .clean(Url::parse("https://a.test/?ref=hn&ab_test=2&page=1").unwrap())
+                .as_str(),
+            "https://a.test/?page=1"
+        );
+    }
+
+    #[test]
+    fn leaves_non_hierarchical_urls() {
+        assert_eq!(
+            clean("mailto:a@b.test?utm_source=x"),
+            "mailto:a@b.test?utm_source=x"
+        );
+    }
+}
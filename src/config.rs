@@ -0,0 +1,835 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Deserializer, Serialize};
+use url::{Position, Url};
+
+use crate::convert::{Bullet, CodeBlock, Emphasis, Heading, LineBreak};
+use crate::extract::Order;
+use crate::images::ImageMode;
+use crate::links::{LinkMode, LinkStyle, RewriteRule};
+use crate::metadata::FrontMatter;
+use crate::preset::Preset;
+
+/// プロジェクト設定ファイルの名前（カレントディレクトリから親へ向かって探す）
+const PROJECT_CONFIG_NAME: &str = ".get-md.toml";
+
+/// 設定ファイルや CLI で指定できる設定値。未指定の項目は `None`。
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Settings {
+    /// プロファイルを適用する URL パターン（`[defaults]` では使わない）
+    #[serde(
+        rename = "match",
+        default,
+        deserialize_with = "one_or_many",
+        skip_serializing
+    )]
+    pub patterns: Vec<String>,
+    #[serde(default, deserialize_with = "optional_one_or_many")]
+    pub selector: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "optional_one_or_many")]
+    pub xpath: Option<Vec<String>>,
+    pub preset: Option<Preset>,
+    #[serde(default, deserialize_with = "optional_one_or_many")]
+    pub exclude: Option<Vec<String>>,
+    pub wait: Option<u64>,
+    pub timeout: Option<u64>,
+    pub headers: Option<BTreeMap<String, String>>,
+    pub front_matter: Option<FrontMatter>,
+    pub heading: Option<Heading>,
+    pub bullet: Option<Bullet>,
+    pub code_block: Option<CodeBlock>,
+    pub emphasis: Option<Emphasis>,
+    pub line_break: Option<LineBreak>,
+    pub link_style: Option<LinkStyle>,
+    pub reference_images: Option<bool>,
+    pub links: Option<LinkMode>,
+    pub link_base: Option<Url>,
+    #[serde(default, deserialize_with = "optional_one_or_many")]
+    pub rewrite_link: Option<Vec<RewriteRule>>,
+    pub clean_urls: Option<bool>,
+    #[serde(default, deserialize_with = "optional_one_or_many")]
+    pub tracking_param: Option<Vec<String>>,
+    pub images: Option<ImageMode>,
+    pub download_images: Option<PathBuf>,
+    pub image_width: Option<u32>,
+    pub no_lazy_images: Option<bool>,
+    pub no_clean_code: Option<bool>,
+    pub keep_prompts: Option<bool>,
+    #[serde(default, deserialize_with = "optional_one_or_many")]
+    pub skip_tag: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "optional_one_or_many")]
+    pub keep_tag: Option<Vec<String>>,
+    pub output: Option<PathBuf>,
+    pub order: Option<Order>,
+    pub pierce: Option<bool>,
+    pub strict: Option<bool>,
+    pub prefer_source: Option<bool>,
+    pub no_cache: Option<bool>,
+}
+
+/// 値が認証情報であるヘッダー
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+
+/// 名前に含まれていれば値を認証情報とみなす語（`X-Api-Key`・`X-Auth-Token` など）
+const SENSITIVE_WORDS: &[&str] = &["token", "secret", "key", "auth", "session", "password"];
+
+/// 伏せた値の代わりに表示する文字列
+const MASK: &str = "***";
+
+impl Settings {
+    /// 認証情報を含むヘッダーの値を伏せる（`--print-config` の出力用）。
+    /// `Bearer xxx` のような値は認証方式だけを残す。
+    pub fn mask_secrets(&mut self) {
+        for (name, value) in self.headers.iter_mut().flatten() {
+            if is_sensitive_header(name) {
+                *value = match value.split_once(' ') {
+                    Some((scheme, _)) if scheme.chars().all(|c| c.is_ascii_alphanumeric()) => {
+                        format!("{scheme} {MASK}")
+                    }
+                    _ => MASK.to_string(),
+                };
+            }
+        }
+    }
+
+    /// `other` で指定された項目を上書きする（ヘッダーはヘッダー名ごとに上書き）
+    pub fn merge(&mut self, other: &Settings) {
+        macro_rules! take {
+            ($($field:ident),*) => {
+                $(if other.$field.is_some() {
+                    self.$field = other.$field.clone();
+                })*
+            };
+        }
+        take!(
+            selector,
+            xpath,
+            preset,
+            exclude,
+            wait,
+            timeout,
+            front_matter,
+            heading,
+            bullet,
+            code_block,
+            emphasis,
+            line_break,
+            link_style,
+            reference_images,
+            links,
+            link_base,
+            rewrite_link,
+            clean_urls,
+            tracking_param,
+            images,
+            download_images,
+            image_width,
+            no_lazy_images,
+            no_clean_code,
+            keep_prompts,
+            skip_tag,
+            keep_tag,
+            output,
+            order,
+            pierce,
+            strict,
+            prefer_source,
+            no_cache
+        );
+
+        if let Some(headers) = &other.headers {
+            let merged = self.headers.get_or_insert_with(BTreeMap::new);
+            for (name, value) in headers {
+                merged.retain(|existing, _| !existing.eq_ignore_ascii_case(name));
+                merged.insert(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// 値 1 つ、または値の配列として書ける値
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> From<OneOrMany<T>> for Vec<T> {
+    fn from(value: OneOrMany<T>) -> Self {
+        match value {
+            OneOrMany::One(s) => vec![s],
+            OneOrMany::Many(v) => v,
+        }
+    }
+}
+
+fn one_or_many<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+    deserializer: D,
+) -> Result<Vec<T>, D::Error> {
+    OneOrMany::deserialize(deserializer).map(Into::into)
+}
+
+fn optional_one_or_many<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<T>>, D::Error> {
+    one_or_many(deserializer).map(Some)
+}
+
+/// 設定ファイル 1 つ分の内容
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(skip)]
+    path: PathBuf,
+    /// すべての URL に適用する既定値
+    #[serde(default)]
+    defaults: Settings,
+    /// URL パターンごとのプロファイル（記述順に適用）
+    #[serde(default)]
+    profile: Vec<Settings>,
+}
+
+impl ConfigFile {
+    fn parse(text: &str, path: &Path) -> Result<Self> {
+        let mut file: ConfigFile = toml::from_str(text)
+            .with_context(|| format!("Invalid config file: {}", path.display()))?;
+        if !file.defaults.patterns.is_empty() {
+            bail!(
+                "'match' is not allowed in [defaults] of config file: {}",
+                path.display()
+            );
+        }
+        if file.profile.iter().any(|p| p.patterns.is_empty()) {
+            bail!(
+                "Every [[profile]] needs a 'match' pattern in config file: {}",
+                path.display()
+            );
+        }
+        file.path = path.to_path_buf();
+        Ok(file)
+    }
+}
+
+/// ユーザー設定とプロジェクト設定を読み込んだもの（優先度の低い順）
+#[derive(Debug, Default)]
+pub struct Config {
+    files: Vec<ConfigFile>,
+}
+
+/// URL に対して解決した設定値と、その由来
+#[derive(Debug, Default)]
+pub struct Resolved {
+    pub settings: Settings,
+    /// 適用した設定の説明（`<ファイル> [defaults]` など）
+    pub sources: Vec<String>,
+}
+
+impl Config {
+    /// ユーザー設定ファイルとプロジェクト設定ファイル（存在するもの）を読み込む
+    pub fn load() -> Result<Self> {
+        let cwd = std::env::current_dir().ok();
+        let paths = user_config_path()
+            .into_iter()
+            .chain(cwd.as_deref().and_then(find_project_config));
+
+        let mut config = Config::default();
+        for path in paths {
+            if !path.is_file() {
+                continue;
+            }
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+            config.files.push(ConfigFile::parse(&text, &path)?);
+        }
+        Ok(config)
+    }
+
+    /// URL に一致する既定値とプロファイルを、ユーザー設定 → プロジェクト設定の順に重ねる
+    pub fn resolve(&self, url: &str) -> Resolved {
+        let url = Url::parse(url).ok();
+        let mut resolved = Resolved::default();
+        for file in &self.files {
+            if file.defaults != Settings::default() {
+                resolved.settings.merge(&file.defaults);
+                resolved
+                    .sources
+                    .push(format!("{} [defaults]", file.path.display()));
+            }
+            for profile in &file.profile {
+                let Some(url) = &url else { continue };
+                if profile.patterns.iter().any(|p| pattern_matches(p, url)) {
+                    resolved.settings.merge(profile);
+                    resolved.sources.push(format!(
+                        "{} [[profile]] match = {:?}",
+                        file.path.display(),
+                        profile.patterns
+                    ));
+                }
+            }
+        }
+        resolved
+    }
+}
+
+/// ユーザー設定ファイルのパス（`$XDG_CONFIG_HOME/get-md/config.toml` など）
+fn user_config_path() -> Option<PathBuf> {
+    let env_dir = |name: &str| {
+        std::env::var_os(name)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    };
+    let base = if cfg!(windows) {
+        env_dir("APPDATA")
+    } else {
+        env_dir("XDG_CONFIG_HOME").or_else(|| env_dir("HOME").map(|h| h.join(".config")))
+    }?;
+    Some(base.join("get-md").join("config.toml"))
+}
+
+/// `dir` から親ディレクトリへ向かってプロジェクト設定ファイルを探す
+fn find_project_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(PROJECT_CONFIG_NAME))
+        .find(|p| p.is_file())
+}
+
+/// プロファイルの `match` パターンが URL に一致するかを判定する。
+///
+/// - `https://example.com/docs/*` のようにスキームを含むものは URL 全体と照合する
+/// - `example.com/docs/*` のように `/` を含むものはホスト以降と照合する
+/// - それ以外はドメインとして扱い、そのドメインとサブドメインに一致する
+///
+/// `*` は任意の文字列（`/` を含む）に一致する。
+fn pattern_matches(pattern: &str, url: &Url) -> bool {
+    let pattern = pattern.trim();
+    if pattern.contains("://") {
+        glob_match(pattern, url.as_str())
+    } else if pattern.contains('/') {
+        glob_match(pattern, &url[Position::BeforeHost..])
+    } else {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let pattern = pattern.to_ascii_lowercase();
+        if pattern.contains('*') {
+            glob_match(&pattern, host)
+        } else {
+            host == pattern
+                || host
+                    .strip_suffix(pattern.as_str())
+                    .is_some_and(|rest| rest.ends_with('.'))
+        }
+    }
+}
+
+/// `*` だけをワイルドカードとする簡易グロブ照合
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    // 直前の `*` の位置と、その `*` に吸収させた末尾のテキスト位置
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if pi < p.len() && p[pi] == t[ti] {
+            pi += 1;
+            ti += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+fn is_sensitive_header(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SENSITIVE_HEADERS.contains(&name.as_str()) || SENSITIVE_WORDS.iter().any(|w| name.contains(w))
+}
+
+/// `-H 'Name: Value'` 形式の HTTP ヘッダー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub name: String,
+    pub value: String,
+}
+
+impl FromStr for Header {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected 'Name: Value', got '{s}'"))?;
+        let name = name.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!("invalid header name in '{s}'"));
+        }
+        Ok(Header {
+            name: name.to_string(),
+            value: value.trim().to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<ConfigFile> {
+        ConfigFile::parse(text, Path::new("/tmp/config.toml"))
+    }
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    // ConfigFile::parse のテスト
+
+    #[test]
+    fn parse_defaults_and_profiles() {
+        let file = parse(
+            r#"
+            [defaults]
+            wait = 3
+
+            [[profile]]
+            match = "docs.rs"
+            selector = ["main", "xpath://article"]
+            exclude = ".ad"
+            order = "document"
+            no-cache = true
+
+            [profile.headers]
+            Authorization = "Bearer x"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(file.defaults.wait, Some(3));
+        assert_eq!(file.profile.len(), 1);
+        let profile = &file.profile[0];
+        assert_eq!(profile.patterns, vec!["docs.rs"]);
+        assert_eq!(
+            profile.selector,
+            Some(vec!["main".to_string(), "xpath://article".to_string()])
+        );
+        assert_eq!(profile.exclude, Some(vec![".ad".to_string()]));
+        assert_eq!(profile.order, Some(Order::Document));
+        assert_eq!(profile.no_cache, Some(true));
+        assert_eq!(
+            profile.headers.as_ref().unwrap()["Authorization"],
+            "Bearer x"
+        );
+    }
+
+    #[test]
+    fn parse_conversion_style() {
+        let file = parse(
+            r#"
+            [defaults]
+            heading = "setext"
+            bullet = "dash"
+            code-block = "indented"
+            emphasis = "underscore"
+            line-break = "backslash"
+            link-style = "reference"
+            reference-images = true
+            skip-tag = "nav"
+            keep-tag = ["svg"]
+            "#,
+        )
+        .unwrap();
+        let defaults = &file.defaults;
+        assert_eq!(defaults.heading, Some(Heading::Setext));
+        assert_eq!(defaults.bullet, Some(Bullet::Dash));
+        assert_eq!(defaults.code_block, Some(CodeBlock::Indented));
+        assert_eq!(defaults.emphasis, Some(Emphasis::Underscore));
+        assert_eq!(defaults.line_break, Some(LineBreak::Backslash));
+        assert_eq!(defaults.link_style, Some(LinkStyle::Reference));
+        assert_eq!(defaults.reference_images, Some(true));
+        assert_eq!(defaults.skip_tag, Some(vec!["nav".to_string()]));
+        assert_eq!(defaults.keep_tag, Some(vec!["svg".to_string()]));
+    }
+
+    #[test]
+    fn parse_link_rewriting() {
+        let file = parse(
+            r#"
+            [defaults]
+            links = "relative-to"
+            link-base = "https://docs.vendor.com/guide/"
+            rewrite-link = 'https://docs\.vendor\.com/(.*)=>./vendor/$1.md'
+            "#,
+        )
+        .unwrap();
+        let defaults = &file.defaults;
+        assert_eq!(defaults.links, Some(LinkMode::RelativeTo));
+        assert_eq!(
+            defaults.link_base.as_ref().map(Url::as_str),
+            Some("https://docs.vendor.com/guide/")
+        );
+        let rules: Vec<String> = defaults
+            .rewrite_link
+            .iter()
+            .flatten()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            rules,
+            vec![r"https://docs\.vendor\.com/(.*)=>./vendor/$1.md"]
+        );
+        assert!(parse("[defaults]\nrewrite-link = ['(=>x']").is_err());
+
+        let file = parse("[defaults]\nclean-urls = true\ntracking-param = \"ref\"").unwrap();
+        assert_eq!(file.defaults.clean_urls, Some(true));
+        assert_eq!(file.defaults.tracking_param, Some(vec!["ref".to_string()]));
+
+        let file = parse("[defaults]\nimages = \"strip\"\ndownload-images = \"img\"").unwrap();
+        assert_eq!(file.defaults.images, Some(ImageMode::Strip));
+        assert_eq!(file.defaults.download_images, Some(PathBuf::from("img")));
+
+        let file = parse("[defaults]\nimage-width = 800\nno-lazy-images = true").unwrap();
+        assert_eq!(file.defaults.image_width, Some(800));
+        assert_eq!(file.defaults.no_lazy_images, Some(true));
+
+        let file = parse("[defaults]\nno-clean-code = false\nkeep-prompts = true").unwrap();
+        assert_eq!(file.defaults.no_clean_code, Some(false));
+        assert_eq!(file.defaults.keep_prompts, Some(true));
+    }
+
+    #[test]
+    fn parse_empty_file() {
+        let file = parse("").unwrap();
+        assert_eq!(file.defaults, Settings::default());
+        assert!(file.profile.is_empty());
+    }
+
+    #[test]
+    fn parse_rejects_unknown_key() {
+        let err = parse("[defaults]\nwiat = 3\n").unwrap_err();
+        assert!(format!("{err:#}").contains("wiat"));
+    }
+
+    #[test]
+    fn parse_rejects_profile_without_match() {
+        assert!(parse("[[profile]]\nwait = 3\n").is_err());
+    }
+
+    #[test]
+    fn parse_rejects_match_in_defaults() {
+        assert!(parse("[defaults]\nmatch = \"example.com\"\n").is_err());
+    }
+
+    // Settings::merge のテスト
+
+    #[test]
+    fn merge_overrides_only_given_fields() {
+        let mut base = Settings {
+            wait: Some(1),
+            timeout: Some(30),
+            ..Default::default()
+        };
+        base.merge(&Settings {
+            wait: Some(5),
+            ..Default::default()
+        });
+        assert_eq!(base.wait, Some(5));
+        assert_eq!(base.timeout, Some(30));
+    }
+
+    #[test]
+    fn merge_overrides_prefer_source() {
+        let mut base = Settings {
+            prefer_source: Some(true),
+            ..Default::default()
+        };
+        base.merge(&Settings::default());
+        assert_eq!(base.prefer_source, Some(true));
+        base.merge(&Settings {
+            prefer_source: Some(false),
+            ..Default::default()
+        });
+        assert_eq!(base.prefer_source, Some(false));
+    }
+
+    #[test]
+    fn merge_covers_every_option() {
+        let mut base = Settings::default();
+        base.merge(&Settings {
+            prefer_source: Some(true),
+            heading: Some(Heading::Setext),
+            emphasis: Some(Emphasis::Underscore),
+            skip_tag: Some(vec!["nav".to_string()]),
+            ..Default::default()
+        });
+        assert_eq!(base.prefer_source, Some(true));
+        assert_eq!(base.heading, Some(Heading::Setext));
+        assert_eq!(base.emphasis, Some(Emphasis::Underscore));
+        assert_eq!(base.skip_tag, Some(vec!["nav".to_string()]));
+    }
+
+    #[test]
+    fn merge_replaces_lists() {
+        let mut base = Settings {
+            exclude: Some(vec![".a".to_string()]),
+            ..Default::default()
+        };
+        base.merge(&Settings {
+            exclude: Some(vec![".b".to_string()]),
+            ..Default::default()
+        });
+        assert_eq!(base.exclude, Some(vec![".b".to_string()]));
+    }
+
+    #[test]
+    fn mask_secrets_hides_credentials() {
+        let mut settings = Settings {
+            headers: Some(BTreeMap::from([
+                ("Authorization".to_string(), "Bearer abc.def".to_string()),
+                ("Cookie".to_string(), "sid=1; theme=dark".to_string()),
+                ("X-Api-Key".to_string(), "k123".to_string()),
+                ("Accept-Language".to_string(), "ja".to_string()),
+            ])),
+            ..Settings::default()
+        };
+        settings.mask_secrets();
+        let headers = settings.headers.unwrap();
+        assert_eq!(headers["Authorization"], "Bearer ***");
+        assert_eq!(headers["Cookie"], "***");
+        assert_eq!(headers["X-Api-Key"], "***");
+        assert_eq!(headers["Accept-Language"], "ja");
+    }
+
+    #[test]
+    fn merge_headers_by_case_insensitive_name() {
+        let mut base = Settings {
+            headers: Some(BTreeMap::from([
+                ("authorization".to_string(), "old".to_string()),
+                ("X-Keep".to_string(), "1".to_string()),
+            ])),
+            ..Default::default()
+        };
+        base.merge(&Settings {
+            headers: Some(BTreeMap::from([(
+                "Authorization".to_string(),
+                "new".to_string(),
+            )])),
+            ..Default::default()
+        });
+        assert_eq!(
+            base.headers,
+            Some(BTreeMap::from([
+                ("Authorization".to_string(), "new".to_string()),
+                ("X-Keep".to_string(), "1".to_string()),
+            ]))
+        );
+    }
+
+    // Config::resolve のテスト
+
+    #[test]
+    fn resolve_layers_user_then_project() {
+        let user = ConfigFile::parse(
+            r#"
+            [defaults]
+            wait = 1
+            timeout = 30
+
+            [[profile]]
+            match = "example.com"
+            selector = "article"
+            "#,
+            Path::new("/home/u/.config/get-md/config.toml"),
+        )
+        .unwrap();
+        let project = ConfigFile::parse(
+            r#"
+            [defaults]
+            wait = 4
+
+            [[profile]]
+            match = "example.com/docs/*"
+            exclude = ".toc"
+            "#,
+            Path::new("/work/.get-md.toml"),
+        )
+        .unwrap();
+        let config = Config {
+            files: vec![user, project],
+        };
+
+        let resolved = config.resolve("https://example.com/docs/intro");
+        assert_eq!(resolved.settings.wait, Some(4));
+        assert_eq!(resolved.settings.timeout, Some(30));
+        assert_eq!(
+            resolved.settings.selector,
+            Some(vec!["article".to_string()])
+        );
+        assert_eq!(resolved.settings.exclude, Some(vec![".toc".to_string()]));
+        assert_eq!(resolved.sources.len(), 4);
+
+        let resolved = config.resolve("https://other.org/");
+        assert_eq!(resolved.settings.selector, None);
+        assert_eq!(resolved.sources.len(), 2);
+    }
+
+    #[test]
+    fn resolve_applies_later_profile_last() {
+        let file = parse(
+            r#"
+            [[profile]]
+            match = "example.com"
+            wait = 1
+
+            [[profile]]
+            match = "https://example.com/slow/*"
+            wait = 9
+            "#,
+        )
+        .unwrap();
+        let config = Config { files: vec![file] };
+        assert_eq!(
+            config
+                .resolve("https://example.com/slow/page")
+                .settings
+                .wait,
+            Some(9)
+        );
+        assert_eq!(
+            config.resolve("https://example.com/fast").settings.wait,
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn resolve_invalid_url_uses_defaults_only() {
+        let file = parse("[defaults]\nwait = 2\n[[profile]]\nmatch = \"*\"\nwait = 5\n").unwrap();
+        let config = Config { files: vec![file] };
+        assert_eq!(config.resolve("not a url").settings.wait, Some(2));
+    }
+
+    // pattern_matches のテスト
+
+    #[test]
+    fn domain_pattern_matches_subdomains() {
+        assert!(pattern_matches(
+            "example.com",
+            &url("https://example.com/a")
+        ));
+        assert!(pattern_matches(
+            "example.com",
+            &url("https://docs.example.com/")
+        ));
+        assert!(!pattern_matches(
+            "example.com",
+            &url("https://notexample.com/")
+        ));
+        assert!(!pattern_matches(
+            "example.com",
+            &url("https://example.com.evil/")
+        ));
+    }
+
+    #[test]
+    fn domain_pattern_is_case_insensitive() {
+        assert!(pattern_matches("Example.COM", &url("https://example.com/")));
+    }
+
+    #[test]
+    fn domain_pattern_with_wildcard() {
+        assert!(pattern_matches(
+            "*.example.com",
+            &url("https://a.example.com/")
+        ));
+        assert!(!pattern_matches(
+            "*.example.com",
+            &url("https://example.com/")
+        ));
+    }
+
+    #[test]
+    fn host_path_pattern() {
+        assert!(pattern_matches(
+            "example.com/docs/*",
+            &url("https://example.com/docs/guide?x=1")
+        ));
+        assert!(!pattern_matches(
+            "example.com/docs/*",
+            &url("https://example.com/blog/")
+        ));
+        assert!(pattern_matches(
+            "localhost:8080/*",
+            &url("http://localhost:8080/index.html")
+        ));
+    }
+
+    #[test]
+    fn full_url_pattern() {
+        assert!(pattern_matches(
+            "https://example.com/*",
+            &url("https://example.com/a/b")
+        ));
+        assert!(!pattern_matches(
+            "https://example.com/*",
+            &url("http://example.com/a")
+        ));
+    }
+
+    // glob_match のテスト
+
+    #[test]
+    fn glob_basic() {
+        assert!(glob_match("abc", "abc"));
+        assert!(!glob_match("abc", "abcd"));
+        assert!(glob_match("a*c", "abbbc"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "a-b-b-c"));
+        assert!(!glob_match("a*b*c", "a-c-b"));
+        assert!(glob_match("日本*", "日本語"));
+    }
+
+    // Header のテスト
+
+    #[test]
+    fn header_parse() {
+        let header: Header = "Authorization: Bearer a:b".parse().unwrap();
+        assert_eq!(header.name, "Authorization");
+        assert_eq!(header.value, "Bearer a:b");
+        assert_eq!(header.to_string(), "Authorization: Bearer a:b");
+    }
+
+    #[test]
+    fn header_parse_empty_value() {
+        let header: Header = "X-Empty:".parse().unwrap();
+        assert_eq!(header.value, "");
+    }
+
+    #[test]
+    fn header_parse_rejects_invalid() {
+        assert!("no colon".parse::<Header>().is_err());
+        assert!(": value".parse::<Header>().is_err());
+        assert!("Bad Name: v".parse::<Header>().is_err());
+    }
+}
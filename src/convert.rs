@@ -0,0 +1,606 @@
+use std::rc::Rc;
+
+use clap::ValueEnum;
+use htmd::element_handler::{HandlerResult, Handlers};
+use htmd::options::{BrStyle, BulletListMarker, CodeBlockStyle, HeadingStyle, Options};
+use htmd::{Element, HtmlToMarkdown};
+use markup5ever_rcdom::{Node, NodeData};
+use serde::{Deserialize, Serialize};
+
+/// 既定で内容ごと取り除くタグ
+pub const DEFAULT_SKIP_TAGS: &[&str] = &["script", "style", "noscript", "svg"];
+
+/// 見出しの書式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Heading {
+    /// `# 見出し`
+    #[default]
+    Atx,
+    /// 見出しの下に `===` / `---` を引く（h1・h2 のみ）
+    Setext,
+}
+
+/// 箇条書きの記号
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Bullet {
+    /// `*`
+    #[default]
+    Asterisk,
+    /// `-`
+    Dash,
+}
+
+/// コードブロックの書式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CodeBlock {
+    /// ```` ``` ```` で囲む
+    #[default]
+    Fenced,
+    /// 4 文字のインデント
+    Indented,
+}
+
+/// 強調の記号
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Emphasis {
+    /// `*em*` / `**strong**`
+    #[default]
+    Asterisk,
+    /// `_em_` / `__strong__`
+    Underscore,
+}
+
+/// 改行（`<br>`）の書式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LineBreak {
+    /// 行末の 2 つの空白
+    #[default]
+    Spaces,
+    /// 行末のバックスラッシュ
+    Backslash,
+}
+
+/// HTML から Markdown への変換の書式
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConvertOptions {
+    pub heading: Heading,
+    pub bullet: Bullet,
+    pub code_block: CodeBlock,
+    pub emphasis: Emphasis,
+    pub line_break: LineBreak,
+    /// 既定に加えて取り除くタグ
+    pub skip_tags: Vec<String>,
+    /// 既定で取り除くタグのうち残すもの
+    pub keep_tags: Vec<String>,
+}
+
+impl ConvertOptions {
+    /// 内容ごと取り除くタグ（既定のタグから `keep_tags` を除き、`skip_tags` を加える）
+    pub fn skipped_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = DEFAULT_SKIP_TAGS
+            .iter()
+            .filter(|tag| !self.keep_tags.iter().any(|k| k.eq_ignore_ascii_case(tag)))
+            .map(ToString::to_string)
+            .collect();
+        for tag in &self.skip_tags {
+            let tag = tag.trim().to_ascii_lowercase();
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        tags
+    }
+
+    /// 書式に従う変換器を作る
+    pub fn converter(&self) -> HtmlToMarkdown {
+        let skipped = self.skipped_tags();
+        let builder = HtmlToMarkdown::builder()
+            .skip_tags(skipped.iter().map(String::as_str).collect())
+            .options(Options {
+                heading_style: match self.heading {
+                    Heading::Atx => HeadingStyle::Atx,
+                    Heading::Setext => HeadingStyle::Setex,
+                },
+                bullet_list_marker: match self.bullet {
+                    Bullet::Asterisk => BulletListMarker::Asterisk,
+                    Bullet::Dash => BulletListMarker::Dash,
+                },
+                code_block_style: match self.code_block {
+                    CodeBlock::Fenced => CodeBlockStyle::Fenced,
+                    CodeBlock::Indented => CodeBlockStyle::Indented,
+                },
+                br_style: match self.line_break {
+                    LineBreak::Spaces => BrStyle::TwoSpaces,
+                    LineBreak::Backslash => BrStyle::Backslash,
+                },
+                ul_bullet_spacing: 1,
+                ol_number_spacing: 1,
+                ..Default::default()
+            });
+        let mut builder = builder
+            .add_handler(vec!["pre"], code_block_handler)
+            .add_handler(vec!["table"], code_table_handler)
+            .add_handler(vec!["math"], math_handler);
+        // htmd の強調は `*` 固定のため、`_` は独自のハンドラで出力する
+        if self.emphasis == Emphasis::Underscore {
+            builder = builder
+                .add_handler(vec!["i", "em"], emphasis_handler("_"))
+                .add_handler(vec!["b", "strong"], emphasis_handler("__"));
+        }
+        builder.build()
+    }
+}
+
+/// 内容を `marker` で囲む強調のハンドラ（前後の空白は記号の外に出す）
+fn emphasis_handler(
+    marker: &'static str,
+) -> impl Fn(&dyn Handlers, Element) -> Option<HandlerResult> + Send + Sync {
+    move |handlers: &dyn Handlers, element: Element| {
+        let content = handlers.walk_children(element.node).content;
+        let trimmed = content.trim();
+        if trimmed.is_empty() {
+            return None;
+        }
+        let leading = &content[..content.len() - content.trim_start().len()];
+        let trailing = &content[content.trim_end().len()..];
+        Some(format!("{leading}{marker}{trimmed}{marker}{trailing}").into())
+    }
+}
+
+/// 言語名を探す祖先要素（`<pre>` を包む `<div>`）の段数
+const LANGUAGE_ANCESTOR_DEPTH: usize = 2;
+
+/// コードブロック（`<pre>`）のハンドラ。
+///
+/// ハイライタ（Prism・highlight.js・Shiki・GitHub・Pygments など）のクラスや属性から言語を求め、
+/// フェンスの後に書く。`<code>` を含まない `<pre>` も、言語が分かればコードブロックにする。
+fn code_block_handler(handlers: &dyn Handlers, element: Element) -> Option<HandlerResult> {
+    if handlers.options().code_block_style != CodeBlockStyle::Fenced {
+        return handlers.fallback(element);
+    }
+    let code = code_child(element.node);
+    let language = code
+        .as_ref()
+        .and_then(node_language)
+        .or_else(|| node_language(element.node))
+        .or_else(|| wrapper_language(element.node));
+    // 1 行ごとの表（highlight.js の行番号プラグインで行番号の列を取り除いたもの）
+    let has_table = has_descendant(element.node, "table");
+
+    if code.is_some() && !has_table {
+        let result = handlers.fallback(element)?;
+        return Some(HandlerResult {
+            content: with_language(&result.content, language.as_deref()),
+            markdown_translated: result.markdown_translated,
+        });
+    }
+    if language.is_none() && !has_table {
+        return handlers.fallback(element);
+    }
+    let language = language.unwrap_or_default();
+    // ハイライト用の `<span>` だけを含む `<pre>` はテキストをそのままコードにする
+    let mut content = String::new();
+    push_text_content(element.node, &mut content);
+    let content = content.trim_matches('\n');
+    let fence = "`".repeat(longest_backtick_run(content).max(2) + 1);
+    Some(format!("\n\n{fence}{language}\n{content}\n{fence}\n\n").into())
+}
+
+/// 子孫のテキストを連結する（`<br>` と表の行の終わりは改行にする）
+fn push_text_content(node: &Node, out: &mut String) {
+    match &node.data {
+        NodeData::Text { contents } => out.push_str(&contents.borrow()),
+        NodeData::Element { name, .. } if &*name.local == "br" => out.push('\n'),
+        _ => {
+            for child in node.children.borrow().iter() {
+                push_text_content(child, out);
+            }
+            if tag_name(node) == Some("tr") && !out.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+    }
+}
+
+/// コードブロックを包む表（Pygments・Rouge の行番号の列を取り除いた後、`<pre>` だけが残るもの）の
+/// ハンドラ。表を外してコードブロックにする。
+fn code_table_handler(handlers: &dyn Handlers, element: Element) -> Option<HandlerResult> {
+    let mut pres = Vec::new();
+    if !collect_sole_pres(element.node, &mut pres) || pres.len() != 1 {
+        return handlers.fallback(element);
+    }
+    handlers.handle(&pres[0])
+}
+
+/// `<pre>` を `pres` に集め、`<pre>` の外に空白以外のテキストや画像などがなければ `true`
+fn collect_sole_pres(node: &Rc<Node>, pres: &mut Vec<Rc<Node>>) -> bool {
+    for child in node.children.borrow().iter() {
+        let sole = match &child.data {
+            NodeData::Text { contents } => contents.borrow().trim().is_empty(),
+            NodeData::Comment { .. } => true,
+            NodeData::Element { name, .. } => match &*name.local {
+                "pre" => {
+                    pres.push(child.clone());
+                    true
+                }
+                "tbody" | "thead" | "tr" | "td" | "th" | "div" | "span" => {
+                    collect_sole_pres(child, pres)
+                }
+                _ => false,
+            },
+            _ => false,
+        };
+        if !sole {
+            return false;
+        }
+    }
+    true
+}
+
+/// 子孫に `tag` の要素があるか
+fn has_descendant(node: &Rc<Node>, tag: &str) -> bool {
+    node.children
+        .borrow()
+        .iter()
+        .any(|child| tag_name(child) == Some(tag) || has_descendant(child, tag))
+}
+
+/// `<pre>` を包む `<div>` が示す言語（行番号の表の中なら表を包む `<div>` も見る）
+fn wrapper_language(node: &Rc<Node>) -> Option<String> {
+    std::iter::successors(parent_node(node), parent_node)
+        .filter(|node| !matches!(tag_name(node), Some("td" | "tr" | "tbody" | "table")))
+        .take(LANGUAGE_ANCESTOR_DEPTH)
+        .take_while(|node| tag_name(node) == Some("div"))
+        .find_map(|node| node_language(&node))
+}
+
+/// 数式（MathML の `<math>`）のハンドラ。TeX をエスケープせずに `$` / `$$` で囲んで出力する
+fn math_handler(handlers: &dyn Handlers, element: Element) -> Option<HandlerResult> {
+    match crate::math::math_markdown(element.node) {
+        Some(markdown) => Some(markdown.into()),
+        None => handlers.fallback(element),
+    }
+}
+
+/// 空白以外の子が `<code>` 1 つだけならその要素
+fn code_child(node: &Rc<Node>) -> Option<Rc<Node>> {
+    let children = node.children.borrow();
+    let mut elements = children.iter().filter(|child| match &child.data {
+        NodeData::Text { contents } => !contents.borrow().trim().is_empty(),
+        NodeData::Comment { .. } => false,
+        _ => true,
+    });
+    let code = elements.next()?;
+    (elements.next().is_none() && tag_name(code) == Some("code")).then(|| code.clone())
+}
+
+pub(crate) fn parent_node(node: &Rc<Node>) -> Option<Rc<Node>> {
+    let parent = node.parent.take();
+    node.parent.set(parent.clone());
+    parent?.upgrade()
+}
+
+pub(crate) fn tag_name(node: &Node) -> Option<&str> {
+    match &node.data {
+        NodeData::Element { name, .. } => Some(&name.local),
+        _ => None,
+    }
+}
+
+/// 要素の `data-language` / `data-lang` 属性やクラスが示す言語
+fn node_language(node: &Rc<Node>) -> Option<String> {
+    let NodeData::Element { attrs, .. } = &node.data else {
+        return None;
+    };
+    let attrs = attrs.borrow();
+    let attr = |name: &str| {
+        attrs
+            .iter()
+            .find(|attr| &*attr.name.local == name)
+            .map(|attr| attr.value.to_string())
+    };
+    if let Some(language) = attr("data-language")
+        .or_else(|| attr("data-lang"))
+        .and_then(|value| language_name(&value))
+    {
+        return Some(language);
+    }
+    class_language(&attr("class")?)
+}
+
+/// クラス名が示す言語。
+///
+/// `language-rust`（Prism・highlight.js・Shiki）、`lang-js`、`highlight-source-python`（GitHub）、
+/// `highlight-python`（Pygments / Sphinx）、`hljs python` / `sourceCode python`（highlight.js・Pandoc）を認識する。
+fn class_language(class: &str) -> Option<String> {
+    let tokens: Vec<&str> = class.split_whitespace().collect();
+    let prefixed = tokens.iter().find_map(|token| {
+        let language = token
+            .strip_prefix("language-")
+            .or_else(|| token.strip_prefix("lang-"))
+            .or_else(|| token.strip_prefix("highlight-source-"))
+            .or_else(|| {
+                token
+                    .strip_prefix("highlight-text-")
+                    .and_then(|rest| rest.split('-').next())
+            })
+            .or_else(|| token.strip_prefix("highlight-"))?;
+        language_name(language)
+    });
+    if prefixed.is_some() {
+        return prefixed;
+    }
+    if !tokens.iter().any(|t| matches!(*t, "hljs" | "sourceCode")) {
+        return None;
+    }
+    tokens
+        .iter()
+        .filter(|t| !matches!(**t, "hljs" | "sourceCode" | "notranslate" | "highlight"))
+        .find_map(|t| language_name(t))
+}
+
+/// 情報文字列に使える言語名（「なし」を表す名前や記号を含むものは除く）
+fn language_name(name: &str) -> Option<String> {
+    let name = name.trim().to_ascii_lowercase();
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '#' | '-' | '_' | '.'));
+    (valid && !matches!(name.as_str(), "none" | "nohighlight" | "default")).then_some(name)
+}
+
+/// 開始フェンスの情報文字列を `language` にする（htmd が `language-*` から書いたものも置き換える）
+fn with_language(markdown: &str, language: Option<&str>) -> String {
+    let start = markdown.len() - markdown.trim_start_matches('\n').len();
+    let line_end = markdown[start..]
+        .find('\n')
+        .map_or(markdown.len(), |i| start + i);
+    let line = &markdown[start..line_end];
+    let Some(marker) = line.chars().next().filter(|c| matches!(c, '`' | '~')) else {
+        return markdown.to_string();
+    };
+    let fence_end = start + line.len() - line.trim_start_matches(marker).len();
+    if fence_end - start < 3 {
+        return markdown.to_string();
+    }
+    format!(
+        "{}{}{}",
+        &markdown[..fence_end],
+        language.unwrap_or_default(),
+        &markdown[line_end..]
+    )
+}
+
+/// 連続するバッククォートの最大長
+fn longest_backtick_run(s: &str) -> usize {
+    s.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(options: &ConvertOptions, html: &str) -> String {
+        options.converter().convert(html).unwrap()
+    }
+
+    // ConvertOptions::skipped_tags のテスト
+
+    #[test]
+    fn skipped_tags_default() {
+        assert_eq!(
+            ConvertOptions::default().skipped_tags(),
+            vec!["script", "style", "noscript", "svg"]
+        );
+    }
+
+    #[test]
+    fn skipped_tags_add_and_keep() {
+        let options = ConvertOptions {
+            skip_tags: vec!["Nav".to_string(), "script".to_string()],
+            keep_tags: vec!["SVG".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            options.skipped_tags(),
+            vec!["script", "style", "noscript", "nav"]
+        );
+    }
+
+    // ConvertOptions::converter のテスト
+
+    #[test]
+    fn converter_default_style() {
+        let md = convert(
+            &ConvertOptions::default(),
+            "<h2>T</h2><ul><li>a</li></ul><p><em>x</em> <strong>y</strong><br>z</p><pre><code>c</code></pre><script>s()</script>",
+        );
+        assert_eq!(md, "## T\n\n* a\n\n*x* **y**  \nz\n\n```\nc\n```");
+    }
+
+    #[test]
+    fn converter_house_style() {
+        let options = ConvertOptions {
+            heading: Heading::Setext,
+            bullet: Bullet::Dash,
+            code_block: CodeBlock::Indented,
+            emphasis: Emphasis::Underscore,
+            line_break: LineBreak::Backslash,
+            ..Default::default()
+        };
+        let md = convert(
+            &options,
+            "<h2>T</h2><ul><li>a</li></ul><p><em>x</em> <strong> y </strong><br>z</p><pre><code>c</code></pre>",
+        );
+        assert_eq!(md, "T\n-\n\n- a\n\n_x_ __y__ \\\nz\n\n    c");
+    }
+
+    // code_block_handler / code_table_handler のテスト
+
+    #[test]
+    fn code_table_with_removed_gutter_becomes_code_block() {
+        let options = ConvertOptions::default();
+        // Pygments（行番号の列は抽出時に取り除かれている）
+        assert_eq!(
+            convert(
+                &options,
+                r#"<div class="highlight-python"><table class="highlighttable"><tbody><tr><td class="code"><div class="highlight"><pre><span class="k">def</span> f():
+    <span class="k">pass</span></pre></div></td></tr></tbody></table></div>"#,
+            ),
+            "```python\ndef f():\n    pass\n```"
+        );
+        // Rouge
+        assert_eq!(
+            convert(
+                &options,
+                r#"<table class="rouge-table"><tbody><tr><td class="rouge-code"><pre><code class="language-ruby">puts 1</code></pre></td></tr></tbody></table>"#,
+            ),
+            "```ruby\nputs 1\n```"
+        );
+    }
+
+    #[test]
+    fn code_table_with_other_content_stays_table() {
+        let options = ConvertOptions::default();
+        // 行番号の列が残っている（--no-clean-code）
+        let md = convert(
+            &options,
+            r#"<table><tbody><tr><td class="linenos"><pre>1</pre></td><td><pre>x</pre></td></tr></tbody></table>"#,
+        );
+        assert!(!md.starts_with("```"), "{md}");
+        let md = convert(
+            &options,
+            "<table><thead><tr><th>a</th></tr></thead><tbody><tr><td>b</td></tr></tbody></table>",
+        );
+        assert!(md.contains("| a |"), "{md}");
+    }
+
+    #[test]
+    fn code_with_line_table_becomes_lines() {
+        // highlight.js の行番号プラグイン（行番号のセルは抽出時に取り除かれている）
+        assert_eq!(
+            convert(
+                &ConvertOptions::default(),
+                r#"<pre><code class="hljs language-js"><table class="hljs-ln"><tbody><tr><td class="hljs-ln-code"><div class="hljs-ln-line">let a = 1;</div></td></tr><tr><td class="hljs-ln-code"><div class="hljs-ln-line">  a += 1;</div></td></tr></tbody></table></code></pre>"#,
+            ),
+            "```js\nlet a = 1;\n  a += 1;\n```"
+        );
+    }
+
+    #[test]
+    fn code_block_language_from_highlighter_classes() {
+        let options = ConvertOptions::default();
+        for html in [
+            r#"<pre><code class="language-rust">fn main() {}</code></pre>"#,
+            r#"<pre class="language-rust"><code>fn main() {}</code></pre>"#,
+            r#"<pre><code class="lang-rust">fn main() {}</code></pre>"#,
+            r#"<pre><code class="hljs rust">fn main() {}</code></pre>"#,
+            r#"<pre class="shiki" data-language="rust"><code>fn main() {}</code></pre>"#,
+            r#"<pre class="sourceCode Rust"><code>fn main() {}</code></pre>"#,
+        ] {
+            assert_eq!(
+                convert(&options, html),
+                "```rust\nfn main() {}\n```",
+                "{html}"
+            );
+        }
+    }
+
+    #[test]
+    fn code_block_language_from_wrapper() {
+        let options = ConvertOptions::default();
+        // GitHub: <code> のない <pre>
+        assert_eq!(
+            convert(
+                &options,
+                r#"<div class="highlight highlight-source-python notranslate"><pre><span class="pl-k">def</span> f(x):
+    <span class="pl-k">return</span> x * 2</pre></div>"#,
+            ),
+            "```python\ndef f(x):\n    return x * 2\n```"
+        );
+        // Sphinx (Pygments)
+        assert_eq!(
+            convert(
+                &options,
+                r#"<div class="highlight-console notranslate"><div class="highlight"><pre><span></span>$ ls</pre></div></div>"#,
+            ),
+            "```console\n$ ls\n```"
+        );
+        assert_eq!(
+            convert(
+                &options,
+                r#"<div class="highlight highlight-text-html-basic"><pre>&lt;p&gt;</pre></div>"#,
+            ),
+            "```html\n<p>\n```"
+        );
+    }
+
+    #[test]
+    fn code_block_without_language_is_unchanged() {
+        let options = ConvertOptions::default();
+        assert_eq!(
+            convert(&options, r#"<pre><code class="hljs">x</code></pre>"#),
+            "```\nx\n```"
+        );
+        assert_eq!(
+            convert(
+                &options,
+                r#"<pre><code class="language-none">x</code></pre>"#
+            ),
+            "```\nx\n```"
+        );
+        assert_eq!(
+            convert(
+                &options,
+                r#"<div class="highlight-default"><pre><code>x</code></pre></div>"#
+            ),
+            "```\nx\n```"
+        );
+        // 言語名を書くのはフェンスの書式のみ
+        let options = ConvertOptions {
+            code_block: CodeBlock::Indented,
+            ..Default::default()
+        };
+        assert_eq!(
+            convert(
+                &options,
+                r#"<pre><code class="language-rust">x</code></pre>"#
+            ),
+            "    x"
+        );
+    }
+
+    #[test]
+    fn code_block_fence_longer_than_content_backticks() {
+        assert_eq!(
+            convert(
+                &ConvertOptions::default(),
+                r#"<div class="highlight-source-md"><pre>```
+x
+```</pre></div>"#,
+            ),
+            "````md\n```\nx\n```\n````"
+        );
+    }
+
+    #[test]
+    fn converter_skip_and_keep_tags() {
+        let options = ConvertOptions {
+            skip_tags: vec!["nav".to_string()],
+            keep_tags: vec!["noscript".to_string()],
+            ..Default::default()
+        };
+        let md = convert(
+            &options,
+            "<nav>menu</nav><p>body</p><noscript>enable JS</noscript>",
+        );
+        assert!(!md.contains("menu"));
+        assert!(md.contains("body"));
+        assert!(md.contains("enable JS"));
+    }
+}
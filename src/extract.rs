@@ -0,0 +1,643 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::html::pick_image_sources;
+use crate::selector::Selector;
+
+/// ノードの文書内位置（パス）を求めるヘルパー
+const PATH_JS: &str = include_str!("js/path.js");
+
+/// `--pierce` 用のシャドウ DOM / フレーム横断ヘルパー
+const PIERCE_JS: &str = include_str!("js/pierce.js");
+
+/// 遅延読み込み画像と `<picture>` の候補をまとめるヘルパー
+const LAZY_IMAGES_JS: &str = include_str!("js/lazy_images.js");
+
+/// コードブロックのノイズ（行番号・コピーボタン・プロンプト）除去のヘルパー
+const CLEAN_CODE_JS: &str = include_str!("js/clean_code.js");
+
+/// KaTeX・MathJax の数式を MathML に置き換えるヘルパー
+const MATH_JS: &str = include_str!("js/math.js");
+
+/// 除外対象の要素に付ける目印の属性
+const EXCLUDE_ATTR: &str = "data-get-md-exclude";
+
+/// コードブロックのノイズに付ける目印の属性
+const NOISE_ATTR: &str = "data-get-md-noise";
+
+/// `--keep-prompts` で残すプロンプト（`$`・`user@host:~$`・`>>>` など）。
+/// JavaScript の正規表現としても同じ意味になる書き方にする。
+const PROMPT_PATTERN: &str = r"^(?:\S*[$#%>❯]|>>>|\.\.\.)$";
+
+/// MathJax 3 の数式に元の TeX を持たせる属性
+const TEX_ATTR: &str = "data-get-md-tex";
+
+/// 抽出スクリプトの生成オプション
+#[derive(Debug, Clone, Default)]
+pub struct ExtractOptions {
+    /// オープンなシャドウルートと同一オリジンのフレームも検索・展開する
+    pub pierce: bool,
+    /// 一致した要素から取り除く要素の CSS セレクタ
+    pub exclude: Vec<String>,
+    /// 遅延読み込み属性を src に昇格させ、srcset / `<picture>` の候補を 1 つに絞る
+    /// （候補の選択は取得後に `html::pick_image_sources` で行う）
+    pub lazy_images: bool,
+    /// srcset の候補選択で優先する幅（px）。この幅以上で最小の候補を選び、未指定なら最大の候補を選ぶ
+    pub image_width: Option<u32>,
+    /// コードブロックから行番号・コピーボタン・選択できない要素（プロンプトなど）を取り除く
+    pub clean_code: bool,
+    /// `clean_code` でも `$` などのプロンプトは残す
+    pub keep_prompts: bool,
+    /// KaTeX・MathJax の数式を、元の TeX を注釈に持つ MathML に置き換える
+    pub math: bool,
+}
+
+/// 複数セレクタの一致結果を出力する順序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Order {
+    /// セレクタの指定順（同一セレクタ内は文書順）
+    #[default]
+    Selector,
+    /// ページ上の出現順
+    Document,
+}
+
+/// セレクタに一致したノード
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Match {
+    /// ノードの HTML
+    pub html: String,
+    /// 文書内位置（祖先からの子インデックス列）。辞書順が文書順に対応する。
+    pub path: Vec<i64>,
+}
+
+impl Match {
+    /// `other` の内側にある（または同一ノードである）かを判定する
+    fn is_within(&self, other: &Match) -> bool {
+        self.path.starts_with(&other.path)
+    }
+}
+
+/// セレクタに一致した全ノードを `Match` の JSON 配列として返す JavaScript を生成する。
+///
+/// 要素以外のノード（XPath で得たテキストノード等）はエスケープしたテキストとして返す。
+/// 除外セレクタに一致する要素は、一致ノード自体からも子孫からも取り除く。
+pub fn extraction_script(selector: &Selector, options: &ExtractOptions) -> String {
+    let (helpers, roots, nodes, copy) = if options.pierce {
+        (
+            PIERCE_JS,
+            "getMdRoots()",
+            format!(
+                "getMdRoots().flatMap(root => {})",
+                selector.nodes_js("root")
+            ),
+            "getMdFlatten(node)",
+        )
+    } else {
+        (
+            "",
+            "[document]",
+            selector.nodes_js("document"),
+            "node.cloneNode(true)",
+        )
+    };
+
+    let mark_excluded = if options.exclude.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "{roots}.forEach(root => root.querySelectorAll({}).forEach(el => el.setAttribute('{EXCLUDE_ATTR}', '')));",
+            crate::escape_js_string(&options.exclude.join(", "))
+        )
+    };
+    let (lazy_helpers, lazy_images) = if options.lazy_images {
+        (LAZY_IMAGES_JS, "getMdLazyImages(wrap);")
+    } else {
+        ("", "")
+    };
+    let (code_helpers, mark_noise, clean_code) = if options.clean_code {
+        let prompts = if options.keep_prompts {
+            format!("new RegExp({})", crate::escape_js_string(PROMPT_PATTERN))
+        } else {
+            "null".to_string()
+        };
+        (
+            CLEAN_CODE_JS,
+            format!(
+                "{roots}.forEach(root => getMdMarkCodeNoise(root, '{NOISE_ATTR}', {prompts}));"
+            ),
+            format!("getMdCleanCode(wrap, '{NOISE_ATTR}');"),
+        )
+    } else {
+        ("", String::new(), String::new())
+    };
+    let (math_helpers, mark_math, convert_math) = if options.math {
+        (
+            MATH_JS,
+            format!("{roots}.forEach(root => getMdMarkMath(root, '{TEX_ATTR}'));"),
+            format!("getMdConvertMath(wrap, '{TEX_ATTR}');"),
+        )
+    } else {
+        ("", String::new(), String::new())
+    };
+    // 手を加える必要がなければ複製せずにそのまま直列化する
+    let serialize = if options.pierce
+        || !options.exclude.is_empty()
+        || options.lazy_images
+        || options.clean_code
+        || options.math
+    {
+        "getMdPrepare(node)"
+    } else {
+        "node.outerHTML"
+    };
+
+    format!(
+        r#"(() => {{
+            {PATH_JS}
+            {helpers}
+            {lazy_helpers}
+            {code_helpers}
+            {math_helpers}
+            {mark_excluded}
+            {mark_noise}
+            {mark_math}
+            const getMdPrepare = (node) => {{
+                const wrap = document.createElement('div');
+                wrap.appendChild({copy});
+                {clean_code}
+                {convert_math}
+                wrap.querySelectorAll('[{EXCLUDE_ATTR}]').forEach(el => el.remove());
+                {lazy_images}
+                return wrap.innerHTML;
+            }};
+            const nodes = {nodes}.filter(node =>
+                node.nodeType !== Node.ELEMENT_NODE || !node.closest('[{EXCLUDE_ATTR}]'));
+            const toHtml = (node) => {{
+                if (node.nodeType === Node.ELEMENT_NODE) return {serialize};
+                const div = document.createElement('div');
+                div.textContent = node.textContent;
+                return div.innerHTML;
+            }};
+            return JSON.stringify(nodes.map(node => ({{
+                html: toHtml(node),
+                path: getMdNodePath(node),
+            }})));
+        }})()"#
+    )
+}
+
+/// 抽出スクリプトの戻り値（JSON 文字列）を解釈する
+pub fn parse_matches(value: Option<&serde_json::Value>) -> Result<Vec<Match>> {
+    match value.and_then(|v| v.as_str()) {
+        Some(json) => serde_json::from_str(json).context("Invalid extraction result"),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// 遅延読み込みを解決した画像の srcset / `<picture>` の候補から 1 つを選ぶ（`lazy_images` 指定時）
+pub fn pick_match_images(matches: &mut [Match], options: &ExtractOptions) {
+    if options.lazy_images {
+        for m in matches {
+            m.html = pick_image_sources(&m.html, options.image_width);
+        }
+    }
+}
+
+/// 一致結果を文書順に並べ、`nth`（1 始まり）が指定されていればその 1 件だけを残す
+pub fn select_nth(mut matches: Vec<Match>, nth: Option<usize>) -> Vec<Match> {
+    matches.sort_by(|a, b| a.path.cmp(&b.path));
+    match nth {
+        Some(n) => matches
+            .into_iter()
+            .nth(n.saturating_sub(1))
+            .into_iter()
+            .collect(),
+        None => matches,
+    }
+}
+
+/// セレクタごとの一致結果を重複除去・並べ替えし、変換対象の HTML 断片にまとめる。
+///
+/// `matches[i]` は i 番目のセレクタの一致結果。それより前（前のセレクタ、または同じ
+/// セレクタの文書順で前）の一致の内側にあるノードと同一ノードは取り除く。連続する同一セレクタの
+/// 一致は 1 つの断片にまとめ、その断片を得たセレクタの番号と組にして返す。
+pub fn arrange_fragments(matches: &[Vec<Match>], order: Order) -> Vec<(usize, String)> {
+    let flat: Vec<(usize, &Match)> = matches
+        .iter()
+        .enumerate()
+        .flat_map(|(i, ms)| ms.iter().map(move |m| (i, m)))
+        .collect();
+
+    let mut kept: Vec<(usize, &Match)> = flat
+        .iter()
+        .enumerate()
+        .filter(|(idx, (_, m))| {
+            !flat
+                .iter()
+                .enumerate()
+                .any(|(other_idx, (_, other))| other_idx < *idx && m.is_within(other))
+        })
+        .map(|(_, entry)| *entry)
+        .collect();
+
+    if order == Order::Document {
+        kept.sort_by(|(_, a), (_, b)| a.path.cmp(&b.path));
+    }
+
+    let mut fragments: Vec<(usize, String)> = Vec::new();
+    for (selector_idx, m) in kept {
+        match fragments.last_mut() {
+            Some((last_idx, html)) if *last_idx == selector_idx => {
+                html.push('\n');
+                html.push_str(&m.html);
+            }
+            _ => fragments.push((selector_idx, m.html.clone())),
+        }
+    }
+    fragments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(html: &str, path: &[i64]) -> Match {
+        Match {
+            html: html.to_string(),
+            path: path.to_vec(),
+        }
+    }
+
+    #[test]
+    fn script_returns_json_with_paths() {
+        let js = extraction_script(
+            &Selector::Css("main".to_string()),
+            &ExtractOptions::default(),
+        );
+        assert!(js.contains("const getMdNodePath"));
+        assert!(js.contains("JSON.stringify"));
+    }
+
+    #[test]
+    fn script_without_pierce_uses_outer_html() {
+        let js = extraction_script(
+            &Selector::Css("main".to_string()),
+            &ExtractOptions::default(),
+        );
+        assert!(js.contains(r#"Array.from(document.querySelectorAll("main"))"#));
+        assert!(js.contains("node.outerHTML"));
+        assert!(!js.contains("getMdRoots"));
+    }
+
+    #[test]
+    fn script_with_pierce_searches_all_roots() {
+        let js = extraction_script(
+            &Selector::Css("main".to_string()),
+            &ExtractOptions {
+                pierce: true,
+                ..Default::default()
+            },
+        );
+        assert!(js.contains("const getMdRoots"));
+        assert!(js.contains(
+            r#"getMdRoots().flatMap(root => Array.from(root.querySelectorAll("main")))"#
+        ));
+        assert!(js.contains("getMdPrepare(node)"));
+        assert!(js.contains("getMdFlatten(node)"));
+    }
+
+    #[test]
+    fn script_with_lazy_images_prepares_copy() {
+        let js = extraction_script(
+            &Selector::Css("main".to_string()),
+            &ExtractOptions {
+                lazy_images: true,
+                ..Default::default()
+            },
+        );
+        assert!(js.contains("const getMdLazyImages"));
+        assert!(js.contains("getMdLazyImages(wrap);"));
+        assert!(js.contains("getMdPrepare(node)"));
+        assert!(js.contains("node.cloneNode(true)"));
+    }
+
+    #[test]
+    fn script_without_lazy_images_has_no_helper() {
+        let js = extraction_script(
+            &Selector::Css("main".to_string()),
+            &ExtractOptions {
+                image_width: Some(800),
+                ..Default::default()
+            },
+        );
+        assert!(!js.contains("getMdLazyImages"));
+    }
+
+    #[test]
+    fn script_with_clean_code_marks_noise() {
+        let js = extraction_script(
+            &Selector::Css("main".to_string()),
+            &ExtractOptions {
+                clean_code: true,
+                ..Default::default()
+            },
+        );
+        assert!(js.contains("const getMdMarkCodeNoise"));
+        assert!(js.contains(
+            "[document].forEach(root => getMdMarkCodeNoise(root, 'data-get-md-noise', null));"
+        ));
+        assert!(js.contains("getMdCleanCode(wrap, 'data-get-md-noise');"));
+        assert!(js.contains("getMdPrepare(node)"));
+    }
+
+    #[test]
+    fn script_with_clean_code_keeps_prompts_in_all_roots() {
+        let js = extraction_script(
+            &Selector::Css("main".to_string()),
+            &ExtractOptions {
+                pierce: true,
+                clean_code: true,
+                keep_prompts: true,
+                ..Default::default()
+            },
+        );
+        assert!(js.contains(
+            r#"getMdRoots().forEach(root => getMdMarkCodeNoise(root, 'data-get-md-noise', new RegExp("^(?:\\S*[$#%>❯]|>>>|\\.\\.\\.)$")));"#
+        ));
+    }
+
+    #[test]
+    fn prompt_pattern_matches_prompts_only() {
+        let prompt = regex::Regex::new(PROMPT_PATTERN).unwrap();
+        for text in ["$", "#", "%", ">", "❯", "user@host:~$", "PS>", ">>>", "..."] {
+            assert!(prompt.is_match(text), "{text}");
+        }
+        for text in ["$ ls", "echo", "1", "12", "->x", "....", ""] {
+            assert!(!prompt.is_match(text), "{text}");
+        }
+    }
+
+    #[test]
+    fn script_with_clean_code_limits_copy_buttons_to_wrapper() {
+        let js = extraction_script(
+            &Selector::Css("main".to_string()),
+            &ExtractOptions {
+                clean_code: true,
+                ..Default::default()
+            },
+        );
+        assert!(js.contains("const container = getMdCodeWrapper(pre, root) || pre;"));
+        // `copyright` や言語タブ・「Run」などのボタンを巻き込まないよう、部分一致や要素名では選ばない
+        assert!(!js.contains("[class*="));
+        assert!(!js.contains("[aria-label*="));
+        assert!(!js.contains("'button'"));
+        assert!(js.contains("'Copy code'"));
+    }
+
+    #[test]
+    fn script_with_math_converts_formulas() {
+        let js = extraction_script(
+            &Selector::Css("main".to_string()),
+            &ExtractOptions {
+                math: true,
+                ..Default::default()
+            },
+        );
+        assert!(js.contains("const getMdConvertMath"));
+        assert!(js.contains("[document].forEach(root => getMdMarkMath(root, 'data-get-md-tex'));"));
+        assert!(js.contains("getMdConvertMath(wrap, 'data-get-md-tex');"));
+        assert!(js.contains("getMdPrepare(node)"));
+    }
+
+    #[test]
+    fn script_with_pierce_evaluates_xpath_per_root() {
+        let js = extraction_script(
+            &Selector::XPath("//main".to_string()),
+            &ExtractOptions {
+                pierce: true,
+                ..Default::default()
+            },
+        );
+        assert!(js.contains("(root.ownerDocument || root).evaluate"));
+    }
+
+    #[test]
+    fn script_without_exclude_does_not_mark() {
+        let js = extraction_script(
+            &Selector::Css("main".to_string()),
+            &ExtractOptions::default(),
+        );
+        assert!(!js.contains("setAttribute('data-get-md-exclude'"));
+    }
+
+    #[test]
+    fn script_with_exclude_marks_and_removes() {
+        let js = extraction_script(
+            &Selector::Css("main".to_string()),
+            &ExtractOptions {
+                exclude: vec![".ad".to_string(), "nav a[href=\"x\"]".to_string()],
+                ..Default::default()
+            },
+        );
+        assert!(js.contains(
+            r#"[document].forEach(root => root.querySelectorAll(".ad, nav a[href=\"x\"]")"#
+        ));
+        assert!(js.contains("node.cloneNode(true)"));
+        assert!(js.contains("getMdPrepare(node)"));
+    }
+
+    #[test]
+    fn script_with_pierce_marks_excluded_in_all_roots() {
+        let js = extraction_script(
+            &Selector::Css("main".to_string()),
+            &ExtractOptions {
+                pierce: true,
+                exclude: vec![".ad".to_string()],
+                ..Default::default()
+            },
+        );
+        assert!(js.contains(r#"getMdRoots().forEach(root => root.querySelectorAll(".ad")"#));
+    }
+
+    // parse_matches のテスト
+
+    #[test]
+    fn parse_matches_reads_json_string() {
+        let value = serde_json::Value::String(r#"[{"html":"<p>a</p>","path":[1,0,-1,2]}]"#.into());
+        assert_eq!(
+            parse_matches(Some(&value)).unwrap(),
+            vec![m("<p>a</p>", &[1, 0, -1, 2])],
+        );
+    }
+
+    #[test]
+    fn parse_matches_without_value_is_empty() {
+        assert!(parse_matches(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_matches_rejects_invalid_json() {
+        let value = serde_json::Value::String("not json".into());
+        assert!(parse_matches(Some(&value)).is_err());
+    }
+
+    // select_nth のテスト
+
+    #[test]
+    fn select_nth_none_keeps_all_in_document_order() {
+        let matches = vec![m("b", &[1, 2]), m("a", &[1, 1])];
+        assert_eq!(
+            select_nth(matches, None),
+            vec![m("a", &[1, 1]), m("b", &[1, 2])]
+        );
+    }
+
+    #[test]
+    fn select_nth_first() {
+        let matches = vec![m("b", &[1, 2]), m("a", &[1, 1])];
+        assert_eq!(select_nth(matches, Some(1)), vec![m("a", &[1, 1])]);
+    }
+
+    #[test]
+    fn select_nth_second() {
+        let matches = vec![m("a", &[1]), m("b", &[2]), m("c", &[3])];
+        assert_eq!(select_nth(matches, Some(2)), vec![m("b", &[2])]);
+    }
+
+    #[test]
+    fn select_nth_out_of_range_is_empty() {
+        let matches = vec![m("a", &[1])];
+        assert!(select_nth(matches, Some(3)).is_empty());
+    }
+
+    // arrange_fragments のテスト
+
+    fn arranged(matches: &[Vec<Match>], order: Order) -> Vec<String> {
+        arrange_fragments(matches, order)
+            .into_iter()
+            .map(|(_, html)| html)
+            .collect()
+    }
+
+    #[test]
+    fn arrange_selector_order_groups_by_selector() {
+        let matches = vec![
+            vec![m("<h2>b</h2>", &[1, 3])],
+            vec![m("<p>a</p>", &[1, 1]), m("<p>c</p>", &[1, 5])],
+        ];
+        assert_eq!(
+            arranged(&matches, Order::Selector),
+            vec!["<h2>b</h2>", "<p>a</p>\n<p>c</p>"],
+        );
+    }
+
+    #[test]
+    fn arrange_document_order_follows_page() {
+        let matches = vec![
+            vec![m("<h2>b</h2>", &[1, 3])],
+            vec![m("<p>a</p>", &[1, 1]), m("<p>c</p>", &[1, 5])],
+        ];
+        assert_eq!(
+            arranged(&matches, Order::Document),
+            vec!["<p>a</p>", "<h2>b</h2>", "<p>c</p>"],
+        );
+    }
+
+    #[test]
+    fn arrange_fragments_keeps_selector_index() {
+        let matches = vec![
+            vec![m("<h2>b</h2>", &[1, 3])],
+            vec![m("<p>a</p>", &[1, 1]), m("<p>c</p>", &[1, 5])],
+        ];
+        assert_eq!(
+            arrange_fragments(&matches, Order::Document),
+            vec![
+                (1, "<p>a</p>".to_string()),
+                (0, "<h2>b</h2>".to_string()),
+                (1, "<p>c</p>".to_string()),
+            ],
+        );
+    }
+
+    #[test]
+    fn arrange_document_order_merges_consecutive_matches() {
+        let matches = vec![
+            vec![m("<p>a</p>", &[1, 1]), m("<p>b</p>", &[1, 2])],
+            vec![m("<h2>c</h2>", &[1, 3])],
+        ];
+        assert_eq!(
+            arranged(&matches, Order::Document),
+            vec!["<p>a</p>\n<p>b</p>", "<h2>c</h2>"],
+        );
+    }
+
+    #[test]
+    fn arrange_removes_match_nested_in_earlier_selector() {
+        let matches = vec![
+            vec![m("<article>x</article>", &[1, 2])],
+            vec![m("<h1>x</h1>", &[1, 2, 0])],
+        ];
+        assert_eq!(
+            arranged(&matches, Order::Selector),
+            vec!["<article>x</article>"],
+        );
+    }
+
+    #[test]
+    fn arrange_keeps_match_nested_in_later_selector() {
+        // 後のセレクタの一致の内側にあるノードは取り除かない
+        let matches = vec![
+            vec![m("<h1>x</h1>", &[1, 2, 0])],
+            vec![m("<article>x</article>", &[1, 2])],
+        ];
+        assert_eq!(
+            arranged(&matches, Order::Selector),
+            vec!["<h1>x</h1>", "<article>x</article>"],
+        );
+    }
+
+    #[test]
+    fn arrange_keeps_first_of_identical_nodes() {
+        let matches = vec![
+            vec![m("<main>first</main>", &[1, 0])],
+            vec![m("<main>second</main>", &[1, 0])],
+        ];
+        assert_eq!(
+            arranged(&matches, Order::Selector),
+            vec!["<main>first</main>"],
+        );
+    }
+
+    #[test]
+    fn arrange_removes_nested_match_of_same_selector() {
+        let matches = vec![vec![
+            m("<div>outer</div>", &[1]),
+            m("<div>inner</div>", &[1, 0]),
+        ]];
+        assert_eq!(
+            arranged(&matches, Order::Document),
+            vec!["<div>outer</div>"],
+        );
+    }
+
+    #[test]
+    fn arrange_keeps_siblings_with_shared_prefix_digits() {
+        // [1, 1] は [1, 10] の祖先ではない
+        let matches = vec![vec![m("a", &[1, 1])], vec![m("b", &[1, 10])]];
+        assert_eq!(arranged(&matches, Order::Selector), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn arrange_orders_shadow_content_before_light_children() {
+        let matches = vec![vec![m("light", &[1, 0])], vec![m("shadow", &[1, -1, 0])]];
+        assert_eq!(arranged(&matches, Order::Document), vec!["shadow", "light"],);
+    }
+
+    #[test]
+    fn arrange_empty_matches() {
+        assert!(arrange_fragments(&[vec![], vec![]], Order::Document).is_empty());
+    }
+}
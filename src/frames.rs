@@ -0,0 +1,132 @@
+use std::cell::Cell;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, anyhow, bail};
+use headless_chrome::Tab;
+use headless_chrome::browser::tab::EventListener;
+use headless_chrome::protocol::cdp::Target;
+use headless_chrome::protocol::cdp::types::Event;
+use serde_json::{Value, json};
+
+/// 自動アタッチ後、アタッチ通知イベントが届くまで待つ時間
+const ATTACH_GRACE: Duration = Duration::from_millis(300);
+
+type Listener = dyn EventListener<Event> + Send + Sync;
+
+/// CDP のフレームターゲットに接続したクロスオリジン iframe
+pub struct FrameSession {
+    pub session_id: String,
+    pub url: String,
+}
+
+/// タブ配下のフレームターゲット（別プロセスで動くクロスオリジン iframe）への接続。
+///
+/// `Target.setAutoAttach` でアタッチしたセッションへ `Target.sendMessageToTarget`
+/// でメッセージを送り、応答は `Target.receivedMessageFromTarget` イベントで受け取る。
+pub struct FrameTargets<'a> {
+    tab: &'a Tab,
+    sessions: Vec<FrameSession>,
+    messages: Receiver<(String, String)>,
+    listener: Weak<Listener>,
+    next_id: Cell<u64>,
+    timeout: Duration,
+}
+
+impl<'a> FrameTargets<'a> {
+    /// タブに属するフレームターゲットへアタッチする
+    pub fn attach(tab: &'a Tab, timeout: Duration) -> Result<Self> {
+        let sessions = Arc::new(Mutex::new(Vec::new()));
+        let (tx, rx) = mpsc::channel();
+
+        let attached = Arc::clone(&sessions);
+        let listener: Arc<Listener> = Arc::new(move |event: &Event| match event {
+            Event::AttachedToTarget(ev) if ev.params.target_info.Type == "iframe" => {
+                attached.lock().unwrap().push(FrameSession {
+                    session_id: ev.params.session_id.clone(),
+                    url: ev.params.target_info.url.clone(),
+                });
+            }
+            Event::ReceivedMessageFromTarget(ev) => {
+                let _ = tx.send((ev.params.session_id.clone(), ev.params.message.clone()));
+            }
+            _ => {}
+        });
+        let listener = tab
+            .add_event_listener(listener)
+            .context("Failed to listen for frame target events")?;
+
+        tab.call_method(Target::SetAutoAttach {
+            auto_attach: true,
+            wait_for_debugger_on_start: false,
+            flatten: Some(false),
+            filter: None,
+        })
+        .context("Failed to attach to frame targets")?;
+        std::thread::sleep(ATTACH_GRACE);
+
+        let sessions = std::mem::take(&mut *sessions.lock().unwrap());
+        Ok(Self {
+            tab,
+            sessions,
+            messages: rx,
+            listener,
+            next_id: Cell::new(0),
+            timeout,
+        })
+    }
+
+    /// アタッチ済みのフレーム
+    pub fn sessions(&self) -> &[FrameSession] {
+        &self.sessions
+    }
+
+    /// 指定フレームで JavaScript 式を評価し、結果の値を返す
+    pub fn evaluate(&self, session_id: &str, expression: &str) -> Result<Value> {
+        let id = self.next_id.get() + 1;
+        self.next_id.set(id);
+        let message = json!({
+            "id": id,
+            "method": "Runtime.evaluate",
+            "params": { "expression": expression, "returnByValue": true },
+        });
+        self.tab
+            .call_method(Target::SendMessageToTarget {
+                message: message.to_string(),
+                session_id: Some(session_id.to_string()),
+                target_id: None,
+            })
+            .context("Failed to send message to frame target")?;
+
+        // 極端なタイムアウト値でもオーバーフローしないよう期限なしとして扱う
+        let deadline = Instant::now().checked_add(self.timeout);
+        loop {
+            let remaining = deadline.map_or(Duration::MAX, |d| {
+                d.saturating_duration_since(Instant::now())
+            });
+            let (from, raw) = self
+                .messages
+                .recv_timeout(remaining)
+                .map_err(|_| anyhow!("Timed out waiting for frame target response"))?;
+            if from != session_id {
+                continue;
+            }
+            let response: Value =
+                serde_json::from_str(&raw).context("Invalid frame target response")?;
+            if response["id"] != id {
+                continue;
+            }
+            if let Some(error) = response.get("error") {
+                bail!("Frame target returned an error: {error}");
+            }
+            return Ok(response["result"]["result"]["value"].clone());
+        }
+    }
+}
+
+impl Drop for FrameTargets<'_> {
+    fn drop(&mut self) {
+        let _ = self.tab.remove_event_listener(&self.listener);
+    }
+}
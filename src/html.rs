@@ -0,0 +1,650 @@
+use std::ops::Range;
+
+use anyhow::{Context, Result};
+use headless_chrome::Tab;
+use pulldown_cmark::{Parser, html};
+
+use crate::images::ImageAction;
+use crate::text::MARKDOWN_OPTIONS_WITH_MATH;
+
+/// HTML 断片からスクリプトを取り除き、相対 URL を解決する JavaScript
+const CLEAN_JS: &str = include_str!("js/clean_html.js");
+
+/// 断片間の区切り
+pub const FRAGMENT_SEPARATOR: &str = "\n<hr>\n";
+
+/// 断片を埋め込んだ整形スクリプトを生成する
+pub fn clean_script(fragments: &[String]) -> String {
+    let list: Vec<String> = fragments
+        .iter()
+        .map(|f| crate::escape_js_string(f))
+        .collect();
+    CLEAN_JS.replace("FRAGMENTS", &format!("[{}]", list.join(", ")))
+}
+
+/// 読み込み済みのページで HTML 断片を整形する
+/// （スクリプト・埋め込み要素の除去、URL 解決、危険なスキームの URL の除去）
+pub fn clean_html(tab: &Tab, fragments: &[String]) -> Result<Vec<String>> {
+    let result = tab
+        .evaluate(&clean_script(fragments), false)
+        .context("Failed to clean HTML fragments")?;
+    let json = result
+        .value
+        .as_ref()
+        .and_then(|v| v.as_str())
+        .context("Failed to clean HTML fragments")?;
+    serde_json::from_str(json).context("Invalid cleaned HTML result")
+}
+
+/// Markdown（サイトの Markdown 版など）を HTML にする
+pub fn markdown_to_html(md: &str) -> String {
+    let mut out = String::new();
+    html::push_html(&mut out, Parser::new_ext(md, MARKDOWN_OPTIONS_WITH_MATH));
+    out.trim_end().to_string()
+}
+
+/// テキストを `<pre>` に入れた HTML にする
+pub fn preformatted(text: &str) -> String {
+    let mut out = String::from("<pre>");
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            _ => out.push(c),
+        }
+    }
+    out.push_str("</pre>");
+    out
+}
+
+/// URL を値に持つ属性（`clean_html.js` と同じ）
+const URL_ATTRIBUTES: &[&str] = &[
+    "href",
+    "src",
+    "poster",
+    "action",
+    "formaction",
+    "cite",
+    "background",
+];
+
+/// HTML のタグの URL 属性（`srcset` の候補を含む）を `map` で書き換える。
+/// `map` が `None` を返した URL はそのまま残す。
+pub fn rewrite_html_urls(html: &str, map: &mut dyn FnMut(&str) -> Option<String>) -> String {
+    let mut edits = Vec::new();
+    for tag in start_tags(html) {
+        for attribute in tag.attributes {
+            if let Some(new) =
+                rewrite_attribute(attribute.name, &html[attribute.value.clone()], map)
+            {
+                edits.push((attribute.value, new));
+            }
+        }
+    }
+    crate::apply_edits(html, edits)
+}
+
+/// HTML の `<img>` / `<source>` の画像（`src` と `srcset` の候補）を `action` に従って書き換える。
+/// 取り除く画像の `<img>` は代替テキストに、`<source>` は空に置き換える。
+pub fn rewrite_html_images(html: &str, action: &mut dyn FnMut(&str) -> ImageAction) -> String {
+    let mut edits = Vec::new();
+    for tag in start_tags(html) {
+        if !tag.name.eq_ignore_ascii_case("img") && !tag.name.eq_ignore_ascii_case("source") {
+            continue;
+        }
+        let mut strip = false;
+        let mut replaced = Vec::new();
+        let mut map = |url: &str| match action(url) {
+            ImageAction::Keep => None,
+            ImageAction::Replace(new) => Some(new),
+            ImageAction::Strip => {
+                strip = true;
+                None
+            }
+        };
+        let mut alt = "";
+        for Attribute { name, value, .. } in tag.attributes {
+            if name.eq_ignore_ascii_case("alt") {
+                alt = &html[value];
+                continue;
+            }
+            let new = if name.eq_ignore_ascii_case("srcset") {
+                rewrite_srcset(&unescape_attribute(&html[value.clone()]), &mut map)
+            } else if name.eq_ignore_ascii_case("src") {
+                let url = unescape_attribute(&html[value.clone()]);
+                Some(url.trim())
+                    .filter(|url| !url.is_empty())
+                    .and_then(&mut map)
+            } else {
+                None
+            };
+            if let Some(new) = new {
+                replaced.push((value, escape_attribute(&new)));
+            }
+        }
+        if strip {
+            let text = if tag.name.eq_ignore_ascii_case("img") {
+                alt
+            } else {
+                ""
+            };
+            edits.push((tag.range, text.to_string()));
+        } else {
+            edits.extend(replaced);
+        }
+    }
+    crate::apply_edits(html, edits)
+}
+
+/// `<img>` の `srcset`（`<picture>` の候補を含む）から 1 つを選んで `src` にし、
+/// `srcset` と `sizes` を取り除く。
+///
+/// `width` 指定時はその幅以上で最小の候補（なければ最大の候補）、未指定なら最大の候補を選ぶ。
+/// x 記述子は `width` 属性（なければ `width`）× 倍率の幅とみなす。
+pub fn pick_image_sources(html: &str, width: Option<u32>) -> String {
+    let mut edits = Vec::new();
+    for tag in start_tags(html) {
+        if !tag.name.eq_ignore_ascii_case("img") {
+            continue;
+        }
+        let attribute = |name: &str| {
+            tag.attributes
+                .iter()
+                .find(|a| a.name.eq_ignore_ascii_case(name))
+        };
+        let Some(srcset) = attribute("srcset") else {
+            continue;
+        };
+        let srcset_value = unescape_attribute(&html[srcset.value.clone()]);
+        let mut candidates = parse_srcset(&srcset_value);
+        let src = attribute("src");
+        let src_value = src.map(|a| unescape_attribute(&html[a.value.clone()]));
+        let src_url = src_value
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty());
+        // プレースホルダーの data: URI は実際の画像（候補または src）があれば候補から外す
+        let is_real = |url: &str| !url.starts_with("data:");
+        if candidates.iter().any(|c| is_real(c.url)) {
+            candidates.retain(|c| is_real(c.url));
+        } else if src_url.is_some_and(is_real) {
+            candidates.clear();
+        }
+        // src 自体も 1x の候補として扱う（srcset が x 記述子のみの場合）
+        if let Some(src) = src_url
+            && !candidates.is_empty()
+            && candidates.iter().all(|c| c.width.is_none())
+            && !candidates.iter().any(|c| c.density == Some(1.0))
+        {
+            candidates.push(SrcsetCandidate {
+                // srcset の外の候補なので位置は使わない
+                start: 0,
+                url: src,
+                width: None,
+                density: Some(1.0),
+            });
+        }
+        let base_width = attribute("width")
+            .and_then(|a| html[a.value.clone()].trim().parse().ok())
+            .filter(|w| *w > 0);
+        if let Some(picked) = pick_candidate(&candidates, width, base_width) {
+            let url = escape_attribute(picked.url);
+            match src {
+                Some(src) => edits.push((src.value.clone(), url)),
+                None => {
+                    let end = tag.range.start + 1 + tag.name.len();
+                    edits.push((end..end, format!(" src=\"{url}\"")));
+                }
+            }
+        }
+        for name in ["srcset", "sizes"] {
+            if let Some(a) = attribute(name) {
+                // 属性の前の空白も取り除く
+                let start = html[..a.range.start].trim_end().len();
+                edits.push((start..a.range.end, String::new()));
+            }
+        }
+    }
+    crate::apply_edits(html, edits)
+}
+
+/// `srcset` の候補
+#[derive(Debug, PartialEq)]
+struct SrcsetCandidate<'a> {
+    /// `srcset` の値の中での URL の開始位置
+    start: usize,
+    url: &'a str,
+    /// w 記述子
+    width: Option<u32>,
+    /// x 記述子（記述子がなければ 1）
+    density: Option<f64>,
+}
+
+/// `srcset` を候補に分ける（URL 内のカンマは区切りとみなさない）
+fn parse_srcset(value: &str) -> Vec<SrcsetCandidate<'_>> {
+    let is_separator = |c: char| c.is_ascii_whitespace() || c == ',';
+    let mut candidates = Vec::new();
+    let mut i = value.len() - value.trim_start_matches(is_separator).len();
+    while i < value.len() {
+        let start = i;
+        let end = value[i..]
+            .find(|c: char| c.is_ascii_whitespace())
+            .map_or(value.len(), |e| i + e);
+        let raw = &value[start..end];
+        let url = raw.trim_end_matches(',');
+        i = end;
+        let mut descriptor = "";
+        if url.len() == raw.len() {
+            // 記述子（`2x` / `640w`）は次の `,` まで
+            let rest = &value[end..];
+            let stop = rest.find(',').unwrap_or(rest.len());
+            descriptor = &rest[..stop];
+            i = end + stop;
+        }
+        i = value.len() - value[i..].trim_start_matches(is_separator).len();
+        let width = descriptor
+            .split_whitespace()
+            .find_map(|d| d.strip_suffix('w')?.parse().ok());
+        let density = descriptor
+            .split_whitespace()
+            .find_map(|d| d.strip_suffix('x')?.parse().ok())
+            .or(width.is_none().then_some(1.0));
+        if !url.is_empty() {
+            candidates.push(SrcsetCandidate {
+                start,
+                url,
+                width,
+                density,
+            });
+        }
+    }
+    candidates
+}
+
+/// 候補から 1 つを選ぶ（`pick_image_sources` を参照）
+fn pick_candidate<'c, 'a>(
+    candidates: &'c [SrcsetCandidate<'a>],
+    width: Option<u32>,
+    base_width: Option<u32>,
+) -> Option<&'c SrcsetCandidate<'a>> {
+    let size = |c: &SrcsetCandidate| match c.width {
+        Some(w) => f64::from(w),
+        None => c.density.unwrap_or(1.0) * f64::from(base_width.or(width).unwrap_or(1)),
+    };
+    let mut sorted: Vec<&SrcsetCandidate> = candidates.iter().collect();
+    sorted.sort_by(|a, b| size(a).total_cmp(&size(b)));
+    width
+        .and_then(|width| sorted.iter().find(|c| size(c) >= f64::from(width)))
+        .or(sorted.last())
+        .copied()
+}
+
+/// HTML の開始タグ
+struct StartTag<'a> {
+    name: &'a str,
+    /// `<` から `>` まで
+    range: Range<usize>,
+    /// 値を持つ属性
+    attributes: Vec<Attribute<'a>>,
+}
+
+/// HTML の属性
+struct Attribute<'a> {
+    name: &'a str,
+    /// 値（引用符の内側）の位置
+    value: Range<usize>,
+    /// 名前から値の終わり（引用符を含む）まで
+    range: Range<usize>,
+}
+
+/// HTML の開始タグを順に読む（コメント内のタグは除く）
+fn start_tags(html: &str) -> Vec<StartTag<'_>> {
+    let bytes = html.as_bytes();
+    let len = bytes.len();
+    let mut tags = Vec::new();
+    let mut i = 0usize;
+
+    while let Some(rel) = html[i..].find('<') {
+        let open = i + rel;
+        if html[open..].starts_with("<!--") {
+            i = html[open..].find("-->").map_or(len, |end| open + end + 3);
+            continue;
+        }
+        if !bytes.get(open + 1).is_some_and(u8::is_ascii_alphabetic) {
+            i = open + 1;
+            continue;
+        }
+
+        // タグ名を読み、属性を順に読む
+        let mut j = open + 1;
+        while j < len && !bytes[j].is_ascii_whitespace() && !matches!(bytes[j], b'>' | b'/') {
+            j += 1;
+        }
+        let name = &html[open + 1..j];
+        let mut attributes = Vec::new();
+        loop {
+            while j < len && (bytes[j].is_ascii_whitespace() || bytes[j] == b'/') {
+                j += 1;
+            }
+            if j >= len || bytes[j] == b'>' {
+                break;
+            }
+            let name_start = j;
+            while j < len
+                && !bytes[j].is_ascii_whitespace()
+                && !matches!(bytes[j], b'=' | b'>' | b'/')
+            {
+                j += 1;
+            }
+            if j == name_start {
+                j += 1;
+                continue;
+            }
+            let name = &html[name_start..j];
+
+            let mut k = j;
+            while k < len && bytes[k].is_ascii_whitespace() {
+                k += 1;
+            }
+            if k >= len || bytes[k] != b'=' {
+                // 値のない属性
+                continue;
+            }
+            k += 1;
+            while k < len && bytes[k].is_ascii_whitespace() {
+                k += 1;
+            }
+            let (value_start, value_end, next) = match bytes.get(k) {
+                Some(&quote @ (b'"' | b'\'')) => {
+                    let end = html[k + 1..].find(quote as char).map_or(len, |e| k + 1 + e);
+                    (k + 1, end, (end + 1).min(len))
+                }
+                _ => {
+                    let mut end = k;
+                    while end < len && !bytes[end].is_ascii_whitespace() && bytes[end] != b'>' {
+                        end += 1;
+                    }
+                    (k, end, end)
+                }
+            };
+            attributes.push(Attribute {
+                name,
+                value: value_start..value_end,
+                range: name_start..next,
+            });
+            j = next;
+        }
+        i = j.min(len);
+        tags.push(StartTag {
+            name,
+            range: open..(i + 1).min(len),
+            attributes,
+        });
+    }
+    tags
+}
+
+/// URL 属性の値の書き換え（書き換えなければ `None`）
+fn rewrite_attribute(
+    name: &str,
+    value: &str,
+    map: &mut dyn FnMut(&str) -> Option<String>,
+) -> Option<String> {
+    let value = unescape_attribute(value);
+    if name.eq_ignore_ascii_case("srcset") {
+        return rewrite_srcset(&value, map).map(|v| escape_attribute(&v));
+    }
+    if !URL_ATTRIBUTES.iter().any(|a| a.eq_ignore_ascii_case(name)) {
+        return None;
+    }
+    let url = value.trim();
+    if url.is_empty() {
+        return None;
+    }
+    map(url).map(|v| escape_attribute(&v))
+}
+
+/// `srcset` の各候補（`url 2x, url 640w`）の URL を書き換える
+fn rewrite_srcset(value: &str, map: &mut dyn FnMut(&str) -> Option<String>) -> Option<String> {
+    let edits: Vec<_> = parse_srcset(value)
+        .into_iter()
+        .filter_map(|c| Some((c.start..c.start + c.url.len(), map(c.url)?)))
+        .collect();
+    (!edits.is_empty()).then(|| crate::apply_edits(value, edits))
+}
+
+fn unescape_attribute(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // clean_script のテスト
+
+    #[test]
+    fn script_embeds_fragments() {
+        let js = clean_script(&["<p class=\"a\">x</p>".to_string()]);
+        assert!(js.contains(r#"JSON.stringify(["<p class=\"a\">x</p>"].map(clean))"#));
+        assert!(js.contains("document.baseURI"));
+    }
+
+    #[test]
+    fn script_removes_embeds_and_unsafe_urls() {
+        let js = clean_script(&[]);
+        assert!(js.contains("'script, noscript, style, iframe, object, embed, base'"));
+        assert!(js.contains("name === 'srcdoc'"));
+        assert!(js.contains("['javascript:', 'vbscript:', 'data:']"));
+    }
+
+    #[test]
+    fn markdown_to_html_keeps_raw_html_for_cleaning() {
+        // 生の HTML はそのまま出力されるため clean_html で整形する
+        assert_eq!(
+            markdown_to_html("<script>alert(1)</script>\n\nx"),
+            "<script>alert(1)</script>\n<p>x</p>"
+        );
+    }
+
+    // markdown_to_html のテスト
+
+    #[test]
+    fn markdown_to_html_renders_tables() {
+        assert_eq!(
+            markdown_to_html("# A\n\n| x |\n| - |\n| 1 |"),
+            "<h1>A</h1>\n<table><thead><tr><th>x</th></tr></thead><tbody>\n<tr><td>1</td></tr>\n</tbody></table>"
+        );
+    }
+
+    // preformatted のテスト
+
+    #[test]
+    fn preformatted_escapes_markup() {
+        assert_eq!(
+            preformatted("{\"a\": \"<b> & c\"}"),
+            "<pre>{\"a\": \"&lt;b&gt; &amp; c\"}</pre>"
+        );
+    }
+
+    // parse_srcset / pick_candidate のテスト
+
+    fn candidates(srcset: &str) -> Vec<(&str, Option<u32>, Option<f64>)> {
+        parse_srcset(srcset)
+            .into_iter()
+            .map(|c| (c.url, c.width, c.density))
+            .collect()
+    }
+
+    fn picked(srcset: &str, width: Option<u32>, base_width: Option<u32>) -> Option<&str> {
+        pick_candidate(&parse_srcset(srcset), width, base_width).map(|c| c.url)
+    }
+
+    #[test]
+    fn parse_srcset_reads_descriptors() {
+        assert_eq!(
+            candidates(" a.png 480w, b.png 2x,c.png,, d.png"),
+            vec![
+                ("a.png", Some(480), None),
+                ("b.png", None, Some(2.0)),
+                ("c.png", None, Some(1.0)),
+                ("d.png", None, Some(1.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_srcset_keeps_commas_inside_urls() {
+        assert_eq!(
+            candidates("data:image/png;base64,AA,BB 1x, /img/a,b.png 2x"),
+            vec![
+                ("data:image/png;base64,AA,BB", None, Some(1.0)),
+                ("/img/a,b.png", None, Some(2.0)),
+            ]
+        );
+        assert!(candidates(" , ").is_empty());
+    }
+
+    #[test]
+    fn pick_candidate_prefers_largest_without_width() {
+        assert_eq!(
+            picked("s.png 320w, l.png 1280w, m.png 640w", None, None),
+            Some("l.png")
+        );
+        assert_eq!(
+            picked("a.png 1x, b.png 3x, c.png 2x", None, None),
+            Some("b.png")
+        );
+    }
+
+    #[test]
+    fn pick_candidate_prefers_smallest_fitting_width() {
+        let srcset = "s.png 320w, l.png 1280w, m.png 640w";
+        assert_eq!(picked(srcset, Some(500), None), Some("m.png"));
+        assert_eq!(picked(srcset, Some(640), None), Some("m.png"));
+        assert_eq!(picked(srcset, Some(2000), None), Some("l.png"));
+    }
+
+    #[test]
+    fn pick_candidate_scales_density_by_base_width() {
+        // width="400" の 2x は 800px とみなす
+        let srcset = "a.png 1x, b.png 2x, c.png 3x";
+        assert_eq!(picked(srcset, Some(700), Some(400)), Some("b.png"));
+        // width 属性がなければ希望の幅を 1x とみなす
+        assert_eq!(picked(srcset, Some(700), None), Some("a.png"));
+    }
+
+    #[test]
+    fn pick_candidate_without_candidates() {
+        assert_eq!(picked("", Some(100), None), None);
+    }
+
+    // pick_image_sources のテスト
+
+    #[test]
+    fn pick_image_sources_sets_src_and_drops_srcset() {
+        assert_eq!(
+            pick_image_sources(
+                r#"<p><img src="s.png" srcset="s.png 320w, l.png 1280w" sizes="100vw" alt="A"></p>"#,
+                None
+            ),
+            r#"<p><img src="l.png" alt="A"></p>"#
+        );
+        assert_eq!(
+            pick_image_sources(r#"<img srcset="s.png 320w, l.png 1280w">"#, Some(300)),
+            r#"<img src="s.png">"#
+        );
+    }
+
+    #[test]
+    fn pick_image_sources_treats_src_as_1x() {
+        assert_eq!(
+            pick_image_sources(
+                r#"<img src="a.png" srcset="a@2x.png 2x" width="300">"#,
+                Some(300)
+            ),
+            r#"<img src="a.png" width="300">"#
+        );
+        assert_eq!(
+            pick_image_sources(
+                r#"<img src="a.png" srcset="a@2x.png 2x" width="300">"#,
+                None
+            ),
+            r#"<img src="a@2x.png" width="300">"#
+        );
+    }
+
+    #[test]
+    fn pick_image_sources_skips_placeholder_data_uris() {
+        assert_eq!(
+            pick_image_sources(
+                r#"<img src="real.png" srcset="data:image/gif;base64,R0lGOD 1x">"#,
+                None
+            ),
+            r#"<img src="real.png">"#
+        );
+        assert_eq!(
+            pick_image_sources(
+                r#"<img srcset="data:image/gif;base64,R0lGOD 1x, big.png 2x">"#,
+                None
+            ),
+            r#"<img src="big.png">"#
+        );
+    }
+
+    #[test]
+    fn pick_image_sources_keeps_images_without_srcset() {
+        let html = r#"<img src="a.png" alt="x"><source srcset="b.png">"#;
+        assert_eq!(pick_image_sources(html, Some(100)), html);
+    }
+
+    // rewrite_html_urls のテスト
+
+    fn resolve(html: &str) -> String {
+        let base = url::Url::parse("https://a.test/docs/page").unwrap();
+        rewrite_html_urls(html, &mut |u| base.join(u).ok().map(String::from))
+    }
+
+    #[test]
+    fn rewrite_html_urls_resolves_url_attributes() {
+        assert_eq!(
+            resolve(
+                r#"<a href="../x" class="y">x</a><img src='i.png' alt="/not-url"><video poster=/p.jpg controls></video>"#
+            ),
+            r#"<a href="https://a.test/x" class="y">x</a><img src='https://a.test/docs/i.png' alt="/not-url"><video poster=https://a.test/p.jpg controls></video>"#
+        );
+    }
+
+    #[test]
+    fn rewrite_html_urls_resolves_srcset_candidates() {
+        assert_eq!(
+            resolve(r#"<img srcset="a.png 1x, /b.png 2x,c.png">"#),
+            r#"<img srcset="https://a.test/docs/a.png 1x, https://a.test/b.png 2x,https://a.test/docs/c.png">"#
+        );
+    }
+
+    #[test]
+    fn rewrite_html_urls_keeps_entities() {
+        assert_eq!(
+            resolve(r#"<a HREF="?a=1&amp;b=2">q</a>"#),
+            r#"<a HREF="https://a.test/docs/page?a=1&amp;b=2">q</a>"#
+        );
+    }
+
+    #[test]
+    fn rewrite_html_urls_skips_text_and_comments() {
+        let html = "<p>href=\"x\" a < b</p><!-- <a href=\"y\"> --></p>";
+        assert_eq!(resolve(html), html);
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use clap::ValueEnum;
+use headless_chrome::Tab;
+use headless_chrome::protocol::cdp::{IO, Network, Page};
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+/// 画像の扱い
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImageMode {
+    /// リンク先の URL のまま残す
+    #[default]
+    Keep,
+    /// 画像を取り除き、代替テキストだけを残す
+    Strip,
+    /// 画像を取得して `data:` URI として埋め込む
+    InlineDataUri,
+}
+
+/// 1 つの画像の書き換え方
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageAction {
+    /// そのまま残す
+    Keep,
+    /// リンク先を置き換える
+    Replace(String),
+    /// 画像を代替テキストに置き換える
+    Strip,
+}
+
+/// 取得した画像
+#[derive(Debug, PartialEq, Eq)]
+pub struct Image {
+    /// `Content-Type`（不明なら空）
+    pub mime: String,
+    pub bytes: Vec<u8>,
+}
+
+/// 1 ページ分の画像の取得・保存（同じ URL は 1 度だけ取得する）
+pub struct ImageStore<'a> {
+    tab: &'a Tab,
+    mode: ImageMode,
+    /// 保存先のディレクトリと、出力する Markdown から見たその相対パス
+    download: Option<(PathBuf, String)>,
+    done: HashMap<String, ImageAction>,
+    warnings: Vec<String>,
+}
+
+impl<'a> ImageStore<'a> {
+    /// 画像を書き換える指定がなければ `None`。
+    /// `output` は Markdown の出力先（相対パスの基準、省略時はカレントディレクトリ）。
+    pub fn new(
+        tab: &'a Tab,
+        mode: ImageMode,
+        download_dir: Option<&Path>,
+        output: Option<&Path>,
+    ) -> Option<Self> {
+        if mode == ImageMode::Keep && download_dir.is_none() {
+            return None;
+        }
+        let download = download_dir.map(|dir| {
+            let from = output
+                .and_then(Path::parent)
+                .filter(|p| !p.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            (dir.to_path_buf(), relative_link(from, dir))
+        });
+        Some(ImageStore {
+            tab,
+            mode,
+            download,
+            done: HashMap::new(),
+            warnings: Vec::new(),
+        })
+    }
+
+    /// `url`（相対 URL は `base_url` で解決する）の画像の書き換え方。
+    /// 取得・保存に失敗した画像は警告を記録してそのまま残す。
+    pub fn action(&mut self, url: &str, base_url: &str) -> ImageAction {
+        if self.mode == ImageMode::Strip {
+            return ImageAction::Strip;
+        }
+        let url = match Url::parse(base_url).and_then(|base| base.join(url)) {
+            Ok(url) => url.to_string(),
+            Err(_) => url.to_string(),
+        };
+        if let Some(action) = self.done.get(&url) {
+            return action.clone();
+        }
+        let action = match self.store(&url) {
+            Ok(action) => action,
+            Err(e) => {
+                self.warnings.push(format!("image not saved: {url}: {e:#}"));
+                ImageAction::Keep
+            }
+        };
+        self.done.insert(url, action.clone());
+        action
+    }
+
+    fn store(&self, url: &str) -> Result<ImageAction> {
+        if self.mode == ImageMode::InlineDataUri && url.starts_with("data:") {
+            return Ok(ImageAction::Keep);
+        }
+        let image = fetch_image(self.tab, url)?;
+        if !image.mime.is_empty() && !image.mime.starts_with("image/") {
+            bail!("not an image ({})", image.mime);
+        }
+        if self.mode == ImageMode::InlineDataUri {
+            return Ok(ImageAction::Replace(data_uri(&image)));
+        }
+        let Some((dir, link)) = &self.download else {
+            return Ok(ImageAction::Keep);
+        };
+        let name = file_name(&image, url);
+        let path = dir.join(&name);
+        if !path.exists() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create image directory: {}", dir.display()))?;
+            std::fs::write(&path, &image.bytes)
+                .with_context(|| format!("Failed to write image: {}", path.display()))?;
+        }
+        Ok(ImageAction::Replace(if link == "." {
+            name
+        } else {
+            format!("{link}/{name}")
+        }))
+    }
+
+    /// 記録した警告を取り出す
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+}
+
+/// 画像を取得する。http(s) はページのフレームから（Cookie 付きで）読み込み、`data:` URL はそのまま解読する。
+pub fn fetch_image(tab: &Tab, url: &str) -> Result<Image> {
+    if let Some(image) = parse_data_url(url) {
+        return Ok(image);
+    }
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        bail!("unsupported URL");
+    }
+    let frame_id = tab
+        .call_method(Page::GetFrameTree(None))
+        .context("Failed to get the page frame")?
+        .frame_tree
+        .frame
+        .id;
+    let resource = tab
+        .call_method(Network::LoadNetworkResource {
+            frame_id: Some(frame_id),
+            url: url.to_string(),
+            options: Network::LoadNetworkResourceOptions {
+                disable_cache: false,
+                include_credentials: true,
+            },
+        })
+        .context("Failed to load image")?
+        .resource;
+    let status = resource.http_status_code.unwrap_or_default() as u32;
+    if !resource.success || !(200..300).contains(&status) {
+        match resource.net_error_name {
+            Some(error) => bail!("{error}"),
+            None => bail!("HTTP {status}"),
+        }
+    }
+    let mime = resource
+        .headers
+        .and_then(|h| h.0)
+        .and_then(|headers| {
+            headers.as_object().and_then(|map| {
+                map.iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                    .and_then(|(_, value)| value.as_str().map(String::from))
+            })
+        })
+        .map(|value| essence(&value))
+        .unwrap_or_default();
+    let handle = resource.stream.context("Empty image response")?;
+
+    let mut bytes = Vec::new();
+    loop {
+        let chunk = tab
+            .call_method(IO::Read {
+                handle: handle.clone(),
+                offset: None,
+                size: Some(1 << 20),
+            })
+            .context("Failed to read image")?;
+        if chunk.base_64_encoded.unwrap_or(false) {
+            bytes.extend(
+                BASE64
+                    .decode(chunk.data.as_bytes())
+                    .context("Invalid image data")?,
+            );
+        } else {
+            bytes.extend(chunk.data.as_bytes());
+        }
+        if chunk.eof {
+            break;
+        }
+    }
+    let _ = tab.call_method(IO::Close { handle });
+    Ok(Image { mime, bytes })
+}
+
+/// `Content-Type` の MIME タイプ部分（小文字）
+fn essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// `data:image/png;base64,...` 形式の URL を解読する。
+/// 本文は（`;base64` の有無に関わらず）パーセントデコードしてから解読する。
+fn parse_data_url(url: &str) -> Option<Image> {
+    let rest = url.strip_prefix("data:")?;
+    let (meta, data) = rest.split_once(',')?;
+    let (mime, base64) = match meta.strip_suffix(";base64") {
+        Some(mime) => (mime, true),
+        None => (meta, false),
+    };
+    let data: Vec<u8> = percent_decode_str(data).collect();
+    let bytes = if base64 {
+        let data: Vec<u8> = data
+            .into_iter()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect();
+        BASE64.decode(data).ok()?
+    } else {
+        data
+    };
+    Some(Image {
+        mime: essence(mime),
+        bytes,
+    })
+}
+
+/// 画像を埋め込む `data:` URI
+pub fn data_uri(image: &Image) -> String {
+    let mime = if image.mime.is_empty() {
+        "application/octet-stream"
+    } else {
+        &image.mime
+    };
+    format!("data:{mime};base64,{}", BASE64.encode(&image.bytes))
+}
+
+/// 保存するファイル名（内容の SHA-256 の先頭 16 桁と拡張子）
+pub fn file_name(image: &Image, url: &str) -> String {
+    let digest = Sha256::digest(&image.bytes);
+    let hash: String = digest[..8].iter().map(|b| format!("{b:02x}")).collect();
+    format!("{hash}.{}", extension(&image.mime, url))
+}
+
+/// MIME タイプ（不明なら URL のパス）から決める拡張子
+fn extension(mime: &str, url: &str) -> String {
+    let known = match mime {
+        "image/png" => Some("png"),
+        "image/jpeg" | "image/jpg" | "image/pjpeg" => Some("jpg"),
+        "image/gif" => Some("gif"),
+        "image/webp" => Some("webp"),
+        "image/avif" => Some("avif"),
+        "image/svg+xml" => Some("svg"),
+        "image/bmp" => Some("bmp"),
+        "image/tiff" => Some("tiff"),
+        "image/x-icon" | "image/vnd.microsoft.icon" => Some("ico"),
+        _ => None,
+    };
+    if let Some(ext) = known {
+        return ext.to_string();
+    }
+    Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            let name = url.path_segments()?.next_back()?.to_string();
+            let (_, ext) = name.rsplit_once('.')?;
+            (!ext.is_empty() && ext.len() <= 5 && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+                .then(|| ext.to_ascii_lowercase())
+        })
+        .unwrap_or_else(|| "bin".to_string())
+}
+
+/// `from` ディレクトリから `to` ディレクトリへの相対パス（`/` 区切り、同じなら `.`）
+pub fn relative_link(from: &Path, to: &Path) -> String {
+    let absolute = |p: &Path| -> Vec<String> {
+        std::path::absolute(p)
+            .unwrap_or_else(|_| p.to_path_buf())
+            .components()
+            .filter_map(|c| match c {
+                Component::CurDir => None,
+                c => Some(c.as_os_str().to_string_lossy().into_owned()),
+            })
+            .fold(Vec::new(), |mut parts, part| {
+                if part == ".." {
+                    parts.pop();
+                } else {
+                    parts.push(part);
+                }
+                parts
+            })
+    };
+    let from = absolute(from);
+    let to = absolute(to);
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let parts: Vec<String> = std::iter::repeat_n("..".to_string(), from.len() - common)
+        .chain(to[common..].iter().map(|p| p.replace(' ', "%20")))
+        .collect();
+    if parts.is_empty() {
+        ".".to_string()
+    } else {
+        parts.join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png() -> Image {
+        Image {
+            mime: "image/png".to_string(),
+            bytes: b"\x89PNG".to_vec(),
+        }
+    }
+
+    // file_name のテスト
+
+    #[test]
+    fn file_name_uses_content_hash_and_type() {
+        let name = file_name(&png(), "https://a.test/logo");
+        assert_eq!(name, "0f4636c78f65d363.png");
+        // 同じ内容なら URL が違っても同じ名前
+        assert_eq!(file_name(&png(), "https://b.test/x.gif?v=1"), name);
+    }
+
+    #[test]
+    fn extension_falls_back_to_url() {
+        assert_eq!(extension("image/jpeg", "https://a.test/a.png"), "jpg");
+        assert_eq!(extension("", "https://a.test/a.WebP?v=2"), "webp");
+        assert_eq!(
+            extension("application/octet-stream", "https://a.test/a"),
+            "bin"
+        );
+    }
+
+    // data: URL のテスト
+
+    #[test]
+    fn data_url_round_trip() {
+        let uri = data_uri(&png());
+        assert_eq!(uri, "data:image/png;base64,iVBORw==");
+        assert_eq!(parse_data_url(&uri), Some(png()));
+        assert_eq!(
+            parse_data_url("data:image/svg+xml,<svg/>").map(|i| i.bytes),
+            Some(b"<svg/>".to_vec())
+        );
+        // `;base64` のない本文はパーセントエンコードされている
+        let svg =
+            parse_data_url("data:image/svg+xml;charset=utf-8,%3Csvg%20xmlns='x'%3E%3C/svg%3E")
+                .unwrap();
+        assert_eq!(svg.mime, "image/svg+xml");
+        assert_eq!(svg.bytes, b"<svg xmlns='x'></svg>".to_vec());
+        assert_eq!(
+            parse_data_url(&data_uri(&svg)).map(|i| i.bytes),
+            Some(b"<svg xmlns='x'></svg>".to_vec())
+        );
+        assert_eq!(
+            parse_data_url("data:image/png;base64,iVBO%0ARw==").map(|i| i.bytes),
+            Some(png().bytes)
+        );
+        assert_eq!(parse_data_url("https://a.test/a.png"), None);
+    }
+
+    // relative_link のテスト
+
+    #[test]
+    fn relative_link_between_directories() {
+        assert_eq!(
+            relative_link(Path::new("docs"), Path::new("docs/img")),
+            "img"
+        );
+        assert_eq!(
+            relative_link(Path::new("docs/en"), Path::new("assets")),
+            "../../assets"
+        );
+        assert_eq!(
+            relative_link(Path::new("."), Path::new("./my images")),
+            "my%20images"
+        );
+        assert_eq!(relative_link(Path::new("a/../b"), Path::new("b")), ".");
+    }
+}
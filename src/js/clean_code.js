@@ -0,0 +1,70 @@
+// コードブロックのノイズ除去ヘルパー。
+// 行番号・コピーボタン・選択できないプロンプトなどに目印を付け、複製から取り除く。
+// 行番号の列を取り除いた後の表は、変換時（convert.rs）にコードブロックに戻す。
+
+/** 行番号の列・要素（Pygments・Prism・highlight.js・Rouge など） */
+const getMdLineNumberSelector = '.linenos, .lineno, .linenodiv, .line-numbers-rows, .hljs-ln-numbers, .rouge-gutter, .gutter';
+/** コピーボタン（クラスは語単位、aria-label は全体で一致させ、言語タブや「Run」などのボタンは残す） */
+const getMdCopyButtonSelector = [
+    'clipboard-copy',
+    ...['Copy', 'Copy code', 'Copy to clipboard', 'Copy code to clipboard']
+        .map(label => `[aria-label="${label}" i]`),
+    ...['copy', 'copy-button', 'copy-btn', 'btn-copy', 'copybtn', 'copy-code', 'copy-code-button',
+        'code-copy', 'code-copy-button', 'clipboard', 'clipboard-button', 'md-clipboard', 'zeroclipboard-container']
+        .map(name => `.${name}`),
+].join(', ');
+
+/** 選択できない（user-select: none）要素かを判定する */
+const getMdIsUnselectable = (el) => {
+    const style = el.ownerDocument.defaultView.getComputedStyle(el);
+    return (style.userSelect || style.webkitUserSelect) === 'none';
+};
+
+/**
+ * ハイライタが <pre> を直接包む要素（表形式の行番号なら表を包む要素）。
+ * 行番号以外の <pre> を 1 つだけ含む場合に限る。
+ */
+const getMdCodeWrapper = (pre, root) => {
+    const table = pre.closest('table');
+    const gutterTable = table && table.querySelector(getMdLineNumberSelector);
+    const wrapper = (gutterTable ? table : pre).parentElement;
+    if (!wrapper || wrapper === root || wrapper.localName === 'body') return null;
+    const pres = Array.from(wrapper.querySelectorAll('pre'))
+        .filter(el => !el.closest(getMdLineNumberSelector));
+    return pres.length === 1 ? wrapper : null;
+};
+
+/** root 内のコードブロックのノイズ要素に attr の目印を付ける（prompts に一致する選択できない要素は残す） */
+const getMdMarkCodeNoise = (root, attr, prompts) => {
+    for (const pre of root.querySelectorAll('pre')) {
+        // 行番号だけの <pre>（表形式の行番号の列）
+        const gutter = pre.closest(getMdLineNumberSelector);
+        if (gutter) {
+            gutter.setAttribute(attr, '');
+            continue;
+        }
+        if (getMdIsUnselectable(pre)) continue;
+        // 行番号とプロンプト（選択できない要素のうち最も外側のもの）
+        for (const el of pre.querySelectorAll('*')) {
+            const unselectable = getMdIsUnselectable(el) && !getMdIsUnselectable(el.parentElement);
+            if (el.matches(getMdLineNumberSelector)) {
+                el.setAttribute(attr, '');
+            } else if (unselectable && !(prompts && prompts.test(el.textContent.trim()))) {
+                el.setAttribute(attr, '');
+            }
+        }
+        // ハイライタの包みの中の行番号とコピーボタン（包みがなければ <pre> の中だけ）
+        const container = getMdCodeWrapper(pre, root) || pre;
+        container.querySelectorAll(getMdLineNumberSelector).forEach(el => el.setAttribute(attr, ''));
+        for (const el of container.querySelectorAll(getMdCopyButtonSelector)) {
+            if (el.localName !== 'pre' && !el.querySelector('pre') && !el.closest('code')) {
+                el.setAttribute(attr, '');
+            }
+        }
+    }
+};
+
+/** 複製から目印の付いた要素を取り除く */
+const getMdCleanCode = (wrap, attr) => {
+    wrap.querySelectorAll(`[${attr}]`).forEach(el => el.remove());
+};
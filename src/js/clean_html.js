@@ -0,0 +1,84 @@
+// 出力する HTML 断片からスクリプトを取り除き、相対 URL をページの URL で絶対 URL にする。
+(() => {
+    const URL_ATTRS = ['href', 'src', 'poster', 'action', 'formaction', 'cite', 'background'];
+    const UNSAFE_PROTOCOLS = ['javascript:', 'vbscript:', 'data:'];
+    const resolve = (value) => {
+        try {
+            return new URL(value, document.baseURI).href;
+        } catch (_) {
+            return value;
+        }
+    };
+    // 解決後の URL のスキームで判定する（画像として読み込む data: の画像だけは残す）
+    const isSafe = (el, name, url) => {
+        let protocol;
+        try {
+            protocol = new URL(url).protocol;
+        } catch (_) {
+            return !/^(?:javascript|vbscript|data):/i.test(url.replace(/[\s\0-\x1f]/g, ''));
+        }
+        if (!UNSAFE_PROTOCOLS.includes(protocol)) return true;
+        return protocol === 'data:' && ['img', 'source'].includes(el.localName)
+            && ['src', 'srcset'].includes(name) && /^data:image\//i.test(url);
+    };
+    // srcset を候補（URL と記述子）に分ける（URL 内のカンマは区切りとみなさない）
+    const parseSrcset = (srcset) => {
+        const candidates = [];
+        let rest = srcset.replace(/^[\s,]+/, '');
+        while (rest) {
+            let url = rest.match(/^\S+/)[0];
+            rest = rest.slice(url.length);
+            let descriptor = '';
+            if (url.endsWith(',')) {
+                url = url.replace(/,+$/, '');
+            } else {
+                const end = rest.indexOf(',');
+                descriptor = (end < 0 ? rest : rest.slice(0, end)).trim();
+                rest = end < 0 ? '' : rest.slice(end + 1);
+            }
+            rest = rest.replace(/^[\s,]+/, '');
+            if (url) candidates.push([url, descriptor]);
+        }
+        return candidates;
+    };
+    const clean = (html) => {
+        const template = document.createElement('template');
+        template.innerHTML = html;
+        const root = template.content;
+        root.querySelectorAll('script, noscript, style, iframe, object, embed, base')
+            .forEach((el) => el.remove());
+        for (const el of root.querySelectorAll('*')) {
+            for (const attr of [...el.attributes]) {
+                const name = attr.name.toLowerCase();
+                const value = attr.value.trim();
+                if (name.startsWith('on') || name === 'srcdoc') {
+                    el.removeAttribute(attr.name);
+                } else if (URL_ATTRS.includes(name)) {
+                    const url = resolve(value);
+                    if (isSafe(el, name, url)) {
+                        el.setAttribute(attr.name, url);
+                    } else {
+                        el.removeAttribute(attr.name);
+                    }
+                } else if (name === 'srcset') {
+                    const candidates = parseSrcset(value)
+                        .map(([url, descriptor]) => [resolve(url), descriptor])
+                        .filter(([url]) => isSafe(el, name, url))
+                        .map((candidate) => candidate.join(' ').trim());
+                    if (candidates.length > 0) {
+                        el.setAttribute(attr.name, candidates.join(', '));
+                    } else {
+                        el.removeAttribute(attr.name);
+                    }
+                } else if (/^(?:javascript|vbscript):/i.test(value.replace(/[\s\0-\x1f]/g, ''))) {
+                    // xlink:href など URL_ATTRS 以外の URL 属性
+                    el.removeAttribute(attr.name);
+                }
+            }
+        }
+        const container = document.createElement('div');
+        container.appendChild(root);
+        return container.innerHTML;
+    };
+    return JSON.stringify(FRAGMENTS.map(clean));
+})()
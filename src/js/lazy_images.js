@@ -0,0 +1,40 @@
+// 遅延読み込み画像のヘルパー。
+// data-src などに置かれた実際の画像を src に移し、<picture> の候補を img の srcset にまとめる。
+// 候補の選択は取得後に Rust 側（html::pick_image_sources）で行う。
+
+/** 実際の画像 URL が置かれることの多い属性 */
+const getMdLazySrcAttrs = ['data-src', 'data-original', 'data-lazy-src', 'data-lazy', 'data-url', 'data-hi-res-src', 'data-actualsrc'];
+const getMdLazySrcsetAttrs = ['data-srcset', 'data-lazy-srcset', 'data-original-set'];
+
+/** src がプレースホルダー（未設定・data: URI・空白画像など）かを判定する */
+const getMdIsPlaceholder = (src) =>
+    !src || /^data:/i.test(src) ||
+    /(blank|spacer|placeholder|transparent|pixel|loading|lazy)[^/]*\.(gif|png|svg|webp)([?#]|$)/i.test(src);
+
+/** root 内の img の遅延読み込み属性を昇格させ、<picture> の候補を img の srcset にまとめる */
+const getMdLazyImages = (root) => {
+    for (const el of root.querySelectorAll('img, source')) {
+        if (getMdIsPlaceholder(el.getAttribute('srcset'))) {
+            const attr = getMdLazySrcsetAttrs.find(a => el.getAttribute(a));
+            if (attr) el.setAttribute('srcset', el.getAttribute(attr));
+        }
+        if (el.localName === 'img' && getMdIsPlaceholder(el.getAttribute('src'))) {
+            const attr = getMdLazySrcAttrs.find(a => el.getAttribute(a) && !/^data:/i.test(el.getAttribute(a)));
+            if (attr) el.setAttribute('src', el.getAttribute(attr));
+        }
+    }
+    for (const img of root.querySelectorAll('img')) {
+        const sources = img.parentElement && img.parentElement.localName === 'picture'
+            ? Array.from(img.parentElement.querySelectorAll(':scope > source'))
+            : [];
+        const srcsets = [img, ...sources]
+            .map(el => (el.getAttribute('srcset') || '').trim())
+            .filter(srcset => srcset);
+        if (srcsets.length > 0) {
+            img.setAttribute('srcset', srcsets.join(', '));
+            // プレースホルダーの src は候補にしない
+            if (getMdIsPlaceholder(img.getAttribute('src'))) img.removeAttribute('src');
+        }
+        sources.forEach(source => source.remove());
+    }
+};
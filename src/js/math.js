@@ -0,0 +1,74 @@
+// 数式のヘルパー。
+// KaTeX・MathJax で描画された数式を、元の TeX を注釈に持つ MathML の <math> に置き換える。
+
+/** MathJax 3 の数式（描画後は DOM に TeX が残らない）に、元の TeX を attr として付ける */
+const getMdMarkMath = (root, attr) => {
+    if (root.nodeType !== Node.DOCUMENT_NODE || !root.body) return;
+    const mathJax = root.defaultView && root.defaultView.MathJax;
+    const doc = mathJax && mathJax.startup && mathJax.startup.document;
+    if (!doc || typeof doc.getMathItemsWithin !== 'function') return;
+    try {
+        for (const item of doc.getMathItemsWithin(root.body)) {
+            if (!item.typesetRoot || !item.inputJax || item.inputJax.name !== 'TeX') continue;
+            item.typesetRoot.setAttribute(attr, item.math);
+            if (item.display) item.typesetRoot.setAttribute(`${attr}-display`, '');
+        }
+    } catch (_) {
+        // MathJax の内部 API が想定と異なる場合は支援用の MathML に任せる
+    }
+};
+
+/** TeX を注釈に持つ <math> 要素を作る */
+const getMdMathElement = (tex, display) => {
+    const ns = 'http://www.w3.org/1998/Math/MathML';
+    const math = document.createElementNS(ns, 'math');
+    if (display) math.setAttribute('display', 'block');
+    const semantics = document.createElementNS(ns, 'semantics');
+    const annotation = document.createElementNS(ns, 'annotation');
+    annotation.setAttribute('encoding', 'application/x-tex');
+    annotation.textContent = tex.trim();
+    semantics.appendChild(annotation);
+    math.appendChild(semantics);
+    return math;
+};
+
+/** 複製内の KaTeX・MathJax の数式を <math> に置き換える */
+const getMdConvertMath = (wrap, attr) => {
+    // MathJax 3（getMdMarkMath で TeX を付けたもの）
+    for (const el of wrap.querySelectorAll(`[${attr}]`)) {
+        el.replaceWith(getMdMathElement(el.getAttribute(attr), el.hasAttribute(`${attr}-display`)));
+    }
+    // MathJax 3（支援用の MathML を持つもの）
+    for (const el of wrap.querySelectorAll('mjx-container')) {
+        const math = el.querySelector('mjx-assistive-mml math');
+        if (!math) continue;
+        if (el.getAttribute('display') === 'true') math.setAttribute('display', 'block');
+        el.replaceWith(math);
+    }
+    // KaTeX
+    for (const el of wrap.querySelectorAll('.katex')) {
+        if (!wrap.contains(el)) continue;
+        const display = el.closest('.katex-display');
+        const annotation = el.querySelector('annotation[encoding="application/x-tex"]');
+        const math = annotation
+            ? getMdMathElement(annotation.textContent, !!display)
+            : el.querySelector('.katex-mathml math');
+        if (!math) continue;
+        if (display) math.setAttribute('display', 'block');
+        (display || el).replaceWith(math);
+    }
+    // MathJax 2（TeX を <script type="math/tex"> に持ち、描画結果は別の要素）
+    const scripts = wrap.querySelectorAll('script[type^="math/tex"]');
+    if (scripts.length > 0) {
+        wrap.querySelectorAll('.MathJax_Preview, .MathJax_Display, .MathJax_SVG_Display, .MJXc-display, [id^="MathJax-Element-"][id$="-Frame"]')
+            .forEach(el => el.remove());
+        for (const script of scripts) {
+            script.replaceWith(getMdMathElement(script.textContent, /mode\s*=\s*display/.test(script.type)));
+        }
+    }
+    // Wikipedia の代替画像（MathML と同じ数式）
+    wrap.querySelectorAll('img.mwe-math-fallback-image-inline, img.mwe-math-fallback-image-display')
+        .forEach(img => {
+            if (img.parentElement && img.parentElement.querySelector('math')) img.remove();
+        });
+};
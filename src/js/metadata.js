@@ -0,0 +1,32 @@
+// フロントマター用にページのメタデータの候補を集める。
+// 優先順位の決定と JSON-LD の解釈は Rust 側で行う。
+(() => {
+    const meta = (attr, name) => {
+        const el = document.querySelector(`meta[${attr}="${name}" i][content]`);
+        return el ? el.content.trim() : null;
+    };
+    const canonical = document.querySelector('link[rel~="canonical" i][href]');
+    const jsonld = [];
+    for (const script of document.querySelectorAll('script[type="application/ld+json" i]')) {
+        try {
+            jsonld.push(JSON.parse(script.textContent));
+        } catch (_) {
+            // 壊れた JSON-LD は無視する
+        }
+    }
+    return JSON.stringify({
+        fetched_at: new Date().toISOString(),
+        title: document.title.trim() || null,
+        og_title: meta('property', 'og:title'),
+        description: meta('name', 'description'),
+        og_description: meta('property', 'og:description'),
+        canonical: canonical ? canonical.href : null,
+        og_url: meta('property', 'og:url'),
+        lang: document.documentElement.lang.trim() || null,
+        og_locale: meta('property', 'og:locale'),
+        author: meta('name', 'author'),
+        article_author: meta('property', 'article:author'),
+        published: meta('property', 'article:published_time') || meta('itemprop', 'datePublished'),
+        jsonld,
+    });
+})()
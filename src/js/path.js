@@ -0,0 +1,26 @@
+// ノードの文書内位置を表すパス（祖先からの子インデックス列）を求める。
+// シャドウルートの中は -1、フレームの中は -2、属性ノードは -3 を挟んで表す。
+const getMdNodePath = (node) => {
+    const path = [];
+    let current = node;
+    if (current.nodeType === Node.ATTRIBUTE_NODE) {
+        path.push(-3);
+        current = current.ownerElement;
+    }
+    while (current && current.parentNode) {
+        const parent = current.parentNode;
+        path.push(Array.prototype.indexOf.call(parent.childNodes, current));
+        if (parent.nodeType === Node.DOCUMENT_FRAGMENT_NODE && parent.host) {
+            path.push(-1);
+            current = parent.host;
+        } else if (parent.nodeType === Node.DOCUMENT_NODE) {
+            const frame = parent.defaultView && parent.defaultView.frameElement;
+            if (!frame) break;
+            path.push(-2);
+            current = frame;
+        } else {
+            current = parent;
+        }
+    }
+    return path.reverse();
+};
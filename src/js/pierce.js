@@ -0,0 +1,60 @@
+// --pierce 用ヘルパー。
+// オープンなシャドウルートと同一オリジンの iframe を横断して検索・展開する。
+
+/** フレーム要素の中身のドキュメントを返す（クロスオリジンの場合は null） */
+const getMdFrameDocument = (el) => {
+    if (el.localName !== 'iframe' && el.localName !== 'frame') return null;
+    try {
+        return el.contentDocument;
+    } catch (_) {
+        return null;
+    }
+};
+
+/** 検索対象となる全ルート（Document / ShadowRoot）を列挙する */
+const getMdRoots = () => {
+    const roots = [];
+    const visit = (root) => {
+        roots.push(root);
+        for (const el of root.querySelectorAll('*')) {
+            if (el.shadowRoot) visit(el.shadowRoot);
+            const doc = getMdFrameDocument(el);
+            if (doc) visit(doc);
+        }
+    };
+    visit(document);
+    return roots;
+};
+
+/** シャドウ DOM とフレームの中身を展開した、切り離された複製を作る */
+const getMdFlatten = (node) => {
+    if (node.nodeType !== Node.ELEMENT_NODE) return node.cloneNode(true);
+
+    // スロットは割り当てられたライト DOM（なければ既定の中身）に置き換える
+    if (node.localName === 'slot') {
+        const fragment = document.createDocumentFragment();
+        const assigned = node.assignedNodes({ flatten: true });
+        const children = assigned.length > 0 ? assigned : Array.from(node.childNodes);
+        for (const child of children) fragment.appendChild(getMdFlatten(child));
+        return fragment;
+    }
+
+    // iframe の中身は HTML パーサが生テキスト扱いするため div として展開する
+    const frameDoc = getMdFrameDocument(node);
+    if (frameDoc) {
+        const div = document.createElement('div');
+        div.setAttribute('data-get-md-frame', node.src || '');
+        if (frameDoc.body) {
+            for (const child of frameDoc.body.childNodes) div.appendChild(getMdFlatten(child));
+        }
+        return div;
+    }
+
+    if (node.localName === 'template') return node.cloneNode(true);
+
+    const clone = node.cloneNode(false);
+    for (const child of (node.shadowRoot || node).childNodes) {
+        clone.appendChild(getMdFlatten(child));
+    }
+    return clone;
+};
@@ -0,0 +1,38 @@
+// Stack Exchange の質問ページから、質問と回答の見出しとスコアを HTML 断片として取り出す。
+// 本文は汎用の抽出と同じ処理（除外・遅延読み込み画像・コードブロック・数式）を加えて別に取り出し、
+// Rust 側で見出しと組にする。質問ページでなければ null を返す。
+(() => {
+    const question = document.querySelector('#question');
+    if (!question) return null;
+
+    const score = (post) => {
+        const count = post.querySelector('.js-vote-count');
+        if (!count) return '';
+        return (count.getAttribute('data-value') || count.textContent).trim();
+    };
+
+    const describe = (level, title, post, notes) => {
+        const wrap = document.createElement('div');
+        const heading = document.createElement(level);
+        heading.textContent = title;
+        wrap.appendChild(heading);
+
+        const meta = document.createElement('p');
+        const em = document.createElement('em');
+        em.textContent = [`Score: ${score(post)}`, ...notes].join(' · ');
+        meta.appendChild(em);
+        wrap.appendChild(meta);
+
+        return { head: wrap.innerHTML, body: !!post.querySelector('.js-post-body') };
+    };
+
+    const title = document.querySelector('#question-header h1');
+    const posts = [
+        describe('h1', title ? title.textContent.trim() : document.title, question, []),
+    ];
+    for (const answer of document.querySelectorAll('#answers .answer')) {
+        const accepted = answer.classList.contains('accepted-answer');
+        posts.push(describe('h2', 'Answer', answer, accepted ? ['Accepted'] : []));
+    }
+    return JSON.stringify(posts);
+})()
@@ -0,0 +1,508 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use pulldown_cmark::{Event, LinkType, Parser, Tag};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::clean::UrlCleaner;
+use crate::text::MARKDOWN_OPTIONS;
+
+/// リンクの書式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkStyle {
+    /// `[text](url)`
+    #[default]
+    Inline,
+    /// `[text][1]` と末尾の `[1]: url`
+    Reference,
+    /// `text[^1]` と末尾の `[^1]: url`
+    Footnote,
+}
+
+/// リンク先 URL の扱い
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkMode {
+    /// ページの URL を基準に絶対 URL にする
+    #[default]
+    Absolute,
+    /// 書かれたまま残す
+    Keep,
+    /// `--link-base` と同じオリジンの URL を、そこからの相対 URL にする
+    RelativeTo,
+}
+
+/// `--rewrite-link 'regex=>replacement'` の書き換え規則
+#[derive(Debug, Clone)]
+pub struct RewriteRule {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl FromStr for RewriteRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, replacement) = s
+            .split_once("=>")
+            .ok_or_else(|| format!("expected 'REGEX=>REPLACEMENT', got '{s}'"))?;
+        let pattern =
+            Regex::new(pattern.trim()).map_err(|e| format!("invalid regex in '{s}': {e}"))?;
+        Ok(RewriteRule {
+            pattern,
+            replacement: replacement.trim().to_string(),
+        })
+    }
+}
+
+impl fmt::Display for RewriteRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}=>{}", self.pattern.as_str(), self.replacement)
+    }
+}
+
+impl PartialEq for RewriteRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern.as_str() == other.pattern.as_str() && self.replacement == other.replacement
+    }
+}
+
+impl<'de> Deserialize<'de> for RewriteRule {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for RewriteRule {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// リンク先 URL の書き換え（相対 URL の解決・`--clean-urls`・書き換え規則・`--links` の適用）
+pub struct LinkRewriter<'a> {
+    /// 相対 URL の基準（ページの URL）
+    base: Option<Url>,
+    mode: LinkMode,
+    /// `--links relative-to` の基準
+    link_base: Option<&'a Url>,
+    rules: &'a [RewriteRule],
+    /// `--clean-urls` 指定時の正規化
+    cleaner: Option<&'a UrlCleaner>,
+}
+
+impl<'a> LinkRewriter<'a> {
+    pub fn new(
+        base_url: &str,
+        mode: LinkMode,
+        link_base: Option<&'a Url>,
+        rules: &'a [RewriteRule],
+        cleaner: Option<&'a UrlCleaner>,
+    ) -> Self {
+        LinkRewriter {
+            base: Url::parse(base_url).ok(),
+            mode,
+            link_base,
+            rules,
+            cleaner,
+        }
+    }
+
+    /// 書き換え後の URL（そのまま残すなら `None`）。
+    ///
+    /// 規則は（`--clean-urls` なら正規化後の）絶対 URL に対して順に試し、
+    /// 最初に一致した規則の置換結果をそのまま使う。
+    pub fn rewrite(&self, url: &str) -> Option<String> {
+        let absolute = self
+            .base
+            .as_ref()
+            .and_then(|base| base.join(url).ok())
+            .map(|absolute| match self.cleaner {
+                Some(cleaner) => cleaner.clean(absolute),
+                None => absolute,
+            });
+        let subject = absolute.as_ref().map_or(url, Url::as_str);
+        if let Some(rule) = self.rules.iter().find(|r| r.pattern.is_match(subject)) {
+            return Some(
+                rule.pattern
+                    .replace(subject, &rule.replacement)
+                    .into_owned(),
+            );
+        }
+        match self.mode {
+            // 書かれたままの URL のうち、絶対 URL だけは正規化する
+            LinkMode::Keep if self.cleaner.is_some() && Url::parse(url).is_ok() => {
+                absolute.map(String::from)
+            }
+            LinkMode::Keep => None,
+            LinkMode::Absolute => absolute.map(String::from),
+            LinkMode::RelativeTo => {
+                let absolute = absolute?;
+                let relative = self
+                    .link_base
+                    .and_then(|base| base.make_relative(&absolute))
+                    .map(|r| if r.is_empty() { "./".to_string() } else { r });
+                Some(relative.unwrap_or_else(|| absolute.into()))
+            }
+        }
+    }
+}
+
+/// 参照先の URL
+struct Definition {
+    /// 参照のラベル（番号）
+    label: usize,
+    url: String,
+    title: String,
+    /// 脚注（`[^n]:`）として書き出すか
+    footnote: bool,
+}
+
+/// 参照先を番号順に集める
+struct Definitions {
+    entries: Vec<Definition>,
+    /// 文書に既にある参照定義のラベル（脚注は `^` 付き）。番号はこれと重ならないように振る。
+    reserved: HashSet<String>,
+}
+
+impl Definitions {
+    /// 既存の参照定義のラベルを避けて番号を振る
+    fn new(md: &str) -> Self {
+        let parser = Parser::new_ext(md, MARKDOWN_OPTIONS);
+        let reserved = parser
+            .reference_definitions()
+            .iter()
+            .map(|(label, _)| label.to_lowercase())
+            .collect();
+        Definitions {
+            entries: Vec::new(),
+            reserved,
+        }
+    }
+
+    /// URL の番号。同じ URL には同じ番号を振る。
+    fn number(&mut self, url: &str, title: &str, footnote: bool) -> usize {
+        if let Some(d) = self
+            .entries
+            .iter()
+            .find(|d| d.url == url && d.footnote == footnote)
+        {
+            return d.label;
+        }
+        let mut label = self.entries.last().map_or(1, |d| d.label + 1);
+        while self.reserved.contains(&label.to_string())
+            || self.reserved.contains(&format!("^{label}"))
+        {
+            label += 1;
+        }
+        self.entries.push(Definition {
+            label,
+            url: url.to_string(),
+            title: title.to_string(),
+            footnote,
+        });
+        label
+    }
+
+    /// インラインのリンク（`images` なら画像も）を参照に置き換える
+    fn rewrite(&mut self, md: &str, style: LinkStyle, images: bool) -> String {
+        let mut out = String::with_capacity(md.len());
+        let mut cursor = 0usize;
+        let events: Vec<_> = Parser::new_ext(md, MARKDOWN_OPTIONS)
+            .into_offset_iter()
+            .collect();
+        for (i, (event, range)) in events.iter().enumerate() {
+            // 書き換えたリンクの内側は飛ばす
+            if range.start < cursor {
+                continue;
+            }
+            let (is_image, dest, title) = match event {
+                Event::Start(Tag::Link {
+                    link_type: LinkType::Inline,
+                    dest_url,
+                    title,
+                    ..
+                }) => (false, dest_url, title),
+                Event::Start(Tag::Image {
+                    link_type: LinkType::Inline,
+                    dest_url,
+                    title,
+                    ..
+                }) if images => (true, dest_url, title),
+                _ => continue,
+            };
+            let Some(link) = crate::inline_link(md, &events, i) else {
+                continue;
+            };
+            let label = &md[link.label];
+            // リンクテキスト内の画像も置き換える
+            let label = if images && !is_image {
+                self.rewrite(label, style, images)
+            } else {
+                label.to_string()
+            };
+
+            out.push_str(&md[cursor..range.start]);
+            let footnote = style == LinkStyle::Footnote && !is_image;
+            let n = self.number(dest, title, footnote);
+            if is_image {
+                out.push_str(&format!("![{label}][{n}]"));
+            } else if footnote {
+                out.push_str(&format!("{label}[^{n}]"));
+            } else {
+                out.push_str(&format!("[{label}][{n}]"));
+            }
+            cursor = range.end;
+        }
+        out.push_str(&md[cursor..]);
+        out
+    }
+
+    /// 末尾に置く定義の行
+    fn lines(&self) -> String {
+        self.entries
+            .iter()
+            .map(|d| {
+                let n = d.label;
+                match (d.footnote, d.title.is_empty()) {
+                    (true, true) => format!("[^{n}]: {}", d.url),
+                    (true, false) => format!("[^{n}]: {}: {}", d.title, d.url),
+                    (false, true) => format!("[{n}]: {}", destination(&d.url)),
+                    (false, false) => format!(
+                        "[{n}]: {} \"{}\"",
+                        destination(&d.url),
+                        d.title.replace('"', "\\\"")
+                    ),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// 参照定義のリンク先。空白や括弧を含む場合は山括弧で囲む。
+fn destination(url: &str) -> String {
+    if url.is_empty() || url.contains([' ', '(', ')', '<', '>']) {
+        format!("<{}>", url.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        url.to_string()
+    }
+}
+
+/// インラインのリンクを参照形式・脚注形式に書き換え、番号付きの定義を末尾に置く。
+///
+/// 同じ URL は 1 つの定義にまとめる。画像は `images` のときだけ参照形式にする。
+pub fn restyle_links(md: &str, style: LinkStyle, images: bool) -> String {
+    if style == LinkStyle::Inline {
+        return md.to_string();
+    }
+    let mut definitions = Definitions::new(md);
+    let body = definitions.rewrite(md, style, images);
+    if definitions.entries.is_empty() {
+        return body;
+    }
+    format!("{}\n\n{}", body.trim_end(), definitions.lines())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // restyle_links のテスト
+
+    #[test]
+    fn inline_style_is_unchanged() {
+        let md = "[a](https://a.test/?utm=1)";
+        assert_eq!(restyle_links(md, LinkStyle::Inline, true), md);
+    }
+
+    #[test]
+    fn reference_style_numbers_and_dedups() {
+        assert_eq!(
+            restyle_links(
+                "See [one](https://a.test/1) and [*two*](https://a.test/2 \"Two\").\n\nAgain [one](https://a.test/1).",
+                LinkStyle::Reference,
+                false
+            ),
+            "See [one][1] and [*two*][2].\n\nAgain [one][1].\n\n[1]: https://a.test/1\n[2]: https://a.test/2 \"Two\""
+        );
+    }
+
+    #[test]
+    fn footnote_style() {
+        assert_eq!(
+            restyle_links(
+                "Read [the docs](https://a.test/docs \"Docs\") or [](https://a.test/).",
+                LinkStyle::Footnote,
+                false
+            ),
+            "Read the docs[^1] or [^2].\n\n[^1]: Docs: https://a.test/docs\n[^2]: https://a.test/"
+        );
+    }
+
+    #[test]
+    fn images_stay_inline_unless_asked() {
+        let md = "![logo](https://a.test/logo.png)\n\n[home](https://a.test/)";
+        assert_eq!(
+            restyle_links(md, LinkStyle::Reference, false),
+            "![logo](https://a.test/logo.png)\n\n[home][1]\n\n[1]: https://a.test/"
+        );
+        assert_eq!(
+            restyle_links(md, LinkStyle::Footnote, true),
+            "![logo][1]\n\nhome[^2]\n\n[1]: https://a.test/logo.png\n[^2]: https://a.test/"
+        );
+    }
+
+    #[test]
+    fn linked_image_keeps_inner_image() {
+        assert_eq!(
+            restyle_links(
+                "[![badge](https://a.test/b.svg)](https://a.test/ci)",
+                LinkStyle::Reference,
+                true
+            ),
+            "[![badge][1]][2]\n\n[1]: https://a.test/b.svg\n[2]: https://a.test/ci"
+        );
+        assert_eq!(
+            restyle_links(
+                "[![badge](https://a.test/b.svg)](https://a.test/ci)",
+                LinkStyle::Reference,
+                false
+            ),
+            "[![badge](https://a.test/b.svg)][1]\n\n[1]: https://a.test/ci"
+        );
+    }
+
+    #[test]
+    fn code_is_not_rewritten() {
+        let md = "`[a](b)`\n\n```\n[c](d)\n```";
+        assert_eq!(restyle_links(md, LinkStyle::Reference, true), md);
+    }
+
+    #[test]
+    fn autolinks_are_kept() {
+        let md = "<https://a.test/>";
+        assert_eq!(restyle_links(md, LinkStyle::Reference, false), md);
+    }
+
+    #[test]
+    fn numbers_skip_existing_definitions() {
+        assert_eq!(
+            restyle_links(
+                "[a](https://a.test/a) [b][1] [c](https://a.test/c)\n\n[1]: https://a.test/b\n[^3]: note",
+                LinkStyle::Reference,
+                false
+            ),
+            "[a][2] [b][1] [c][4]\n\n[1]: https://a.test/b\n[^3]: note\n\n[2]: https://a.test/a\n[4]: https://a.test/c"
+        );
+    }
+
+    #[test]
+    fn destination_with_spaces_uses_angle_brackets() {
+        assert_eq!(
+            restyle_links("[a](<./a b>)", LinkStyle::Reference, false),
+            "[a][1]\n\n[1]: <./a b>"
+        );
+    }
+
+    #[test]
+    fn links_in_table_cells() {
+        assert_eq!(
+            restyle_links(
+                "| a |\n| - |\n| [x](https://a.test/x) |",
+                LinkStyle::Reference,
+                false
+            ),
+            "| a |\n| - |\n| [x][1] |\n\n[1]: https://a.test/x"
+        );
+    }
+
+    // RewriteRule のテスト
+
+    #[test]
+    fn rewrite_rule_parse() {
+        let rule: RewriteRule = "^https://docs\\.vendor\\.com/(.*) => ./vendor/$1.md"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            rule.to_string(),
+            "^https://docs\\.vendor\\.com/(.*)=>./vendor/$1.md"
+        );
+        assert!("no-arrow".parse::<RewriteRule>().is_err());
+        assert!("(=>x".parse::<RewriteRule>().is_err());
+    }
+
+    // LinkRewriter のテスト
+
+    const PAGE: &str = "https://docs.vendor.com/guide/intro";
+
+    #[test]
+    fn rewriter_modes() {
+        let absolute = LinkRewriter::new(PAGE, LinkMode::Absolute, None, &[], None);
+        assert_eq!(
+            absolute.rewrite("setup").as_deref(),
+            Some("https://docs.vendor.com/guide/setup")
+        );
+        let keep = LinkRewriter::new(PAGE, LinkMode::Keep, None, &[], None);
+        assert_eq!(keep.rewrite("setup"), None);
+
+        let base = Url::parse("https://docs.vendor.com/guide/").unwrap();
+        let relative = LinkRewriter::new(PAGE, LinkMode::RelativeTo, Some(&base), &[], None);
+        assert_eq!(relative.rewrite("/guide/a/b").as_deref(), Some("a/b"));
+        assert_eq!(relative.rewrite("/api").as_deref(), Some("../api"));
+        assert_eq!(relative.rewrite("./").as_deref(), Some("./"));
+        assert_eq!(relative.rewrite("#top").as_deref(), Some("intro#top"));
+        assert_eq!(
+            relative.rewrite("https://other.test/x").as_deref(),
+            Some("https://other.test/x")
+        );
+    }
+
+    #[test]
+    fn rewriter_applies_first_matching_rule() {
+        let rules: Vec<RewriteRule> = vec![
+            "^https://docs\\.vendor\\.com/(.*)=>./vendor/$1.md"
+                .parse()
+                .unwrap(),
+            "vendor=>never".parse().unwrap(),
+        ];
+        let keep = LinkRewriter::new(PAGE, LinkMode::Keep, None, &rules, None);
+        assert_eq!(
+            keep.rewrite("../api/auth").as_deref(),
+            Some("./vendor/api/auth.md")
+        );
+        assert_eq!(keep.rewrite("https://other.test/"), None);
+    }
+
+    #[test]
+    fn rewriter_cleans_urls_before_rules() {
+        let cleaner = UrlCleaner::new(&[]);
+        let rules: Vec<RewriteRule> = vec![
+            "^https://docs\\.vendor\\.com/api\\?v=1$=>./api.md"
+                .parse()
+                .unwrap(),
+        ];
+        let absolute = LinkRewriter::new(PAGE, LinkMode::Absolute, None, &rules, Some(&cleaner));
+        assert_eq!(
+            absolute.rewrite("/api?v=1&utm_source=x").as_deref(),
+            Some("./api.md")
+        );
+        assert_eq!(
+            absolute.rewrite("a?fbclid=1").as_deref(),
+            Some("https://docs.vendor.com/guide/a")
+        );
+        let keep = LinkRewriter::new(PAGE, LinkMode::Keep, None, &[], Some(&cleaner));
+        assert_eq!(keep.rewrite("a?fbclid=1"), None);
+        assert_eq!(
+            keep.rewrite("https://x.test/?gclid=1").as_deref(),
+            Some("https://x.test/")
+        );
+    }
+}
@@ -1,33 +1,207 @@
+mod adapter;
+mod clean;
+mod config;
+mod convert;
+mod extract;
+mod frames;
+mod html;
+mod images;
+mod links;
+mod math;
+mod metadata;
+mod plain;
+mod preset;
 mod progress;
+mod report;
+mod selector;
+mod source;
+mod text;
 
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::{self, Write};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use std::time::Duration;
+use std::process::{Command, ExitCode};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result, bail};
-use clap::Parser;
-use headless_chrome::protocol::cdp::Network;
-use headless_chrome::{Browser, LaunchOptions};
-use url::Url;
-
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser};
+use headless_chrome::browser::tab::RequestPausedDecision;
+use headless_chrome::protocol::cdp::Fetch::events::RequestPausedEvent;
+use headless_chrome::protocol::cdp::{Fetch, Network};
+use headless_chrome::{Browser, LaunchOptions, Tab};
+use pulldown_cmark::{Event, LinkType, Tag};
+use url::{Origin, Url};
+
+use crate::adapter::{Extracted, find_adapter};
+use crate::clean::UrlCleaner;
+use crate::config::{Config, Header, Settings};
+use crate::convert::{Bullet, CodeBlock, ConvertOptions, Emphasis, Heading, LineBreak};
+use crate::extract::{
+    ExtractOptions, Match, Order, arrange_fragments, extraction_script, parse_matches,
+    pick_match_images, select_nth,
+};
+use crate::frames::FrameTargets;
+use crate::html::{
+    clean_html, markdown_to_html, preformatted, rewrite_html_images, rewrite_html_urls,
+};
+use crate::images::{ImageAction, ImageMode, ImageStore};
+use crate::links::{LinkMode, LinkRewriter, LinkStyle, RewriteRule, restyle_links};
+use crate::metadata::{FrontMatter, collect_metadata};
+use crate::plain::{PlainKind, read_plain_document};
+use crate::preset::{Preset, detect, detection_script, parse_signals};
 use crate::progress::Progress;
+use crate::report::{FragmentReport, OutputFormat, PageReport, SelectorReport, Timings, render};
+use crate::selector::{
+    Expectation, Selector, SelectorMismatch, SelectorSpec, check_match_counts,
+    find_unknown_expectation,
+};
+use crate::source::fetch_source;
+use crate::text::{MARKDOWN_OPTIONS, markdown_to_text};
 
 /// ブラウザで URL を取得し、指定要素を Markdown に変換する。
 /// システムにインストールされた Chrome/Chromium を利用し、
 /// JavaScript で描画されるページにも対応する。
-#[derive(Parser)]
+#[derive(Clone, Parser)]
 #[command(version, about)]
 struct Cli {
-    /// 取得対象の URL
-    url: String,
+    /// 取得対象の URL（複数指定可）
+    #[arg(value_name = "URL", required = true)]
+    urls: Vec<String>,
 
     /// Markdown 変換対象の CSS セレクタ（複数指定可）。
+    /// `xpath:` を前置すると XPath 式として評価する。
+    /// `a || b` のように区切ると、最初に一致したセレクタを採用する。
     /// 省略時はページ全体（body）を対象にする。
     #[arg(short, long)]
     selector: Vec<String>,
 
+    /// Markdown 変換対象の XPath 式（複数指定可、`||` で代替指定可）
+    #[arg(long)]
+    xpath: Vec<String>,
+
+    /// ドキュメント生成ツール向けの組み込みプリセット（auto: ページから自動判定）。
+    /// 本文セレクタ（--selector / --xpath 未指定時）・除外セレクタ・待機条件を適用する。
+    #[arg(long, value_enum)]
+    preset: Option<Preset>,
+
+    /// 一致した要素から取り除く要素の CSS セレクタ（複数指定可）
+    #[arg(long)]
+    exclude: Vec<String>,
+
+    /// 各セレクタの最初の一致要素だけを対象にする（`--nth 1` と同じ）
+    #[arg(long, conflicts_with = "nth")]
+    first: bool,
+
+    /// 各セレクタの N 番目（1 始まり、文書順）の一致要素だけを対象にする
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    nth: Option<u64>,
+
+    /// タイトル・URL・取得日時・説明・正規 URL・言語・著者・公開日を
+    /// フロントマターとして先頭に付ける（yaml / toml）
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    front_matter: Option<FrontMatter>,
+
+    /// 見出しの書式（atx: `#`、setext: 下線、h3 以降は atx）
+    #[arg(long, value_enum, default_value_t = Heading::Atx)]
+    heading: Heading,
+
+    /// 箇条書きの記号（asterisk: `*`、dash: `-`）
+    #[arg(long, value_enum, default_value_t = Bullet::Asterisk)]
+    bullet: Bullet,
+
+    /// コードブロックの書式（fenced: ```` ``` ````、indented: 4 文字のインデント）
+    #[arg(long, value_enum, default_value_t = CodeBlock::Fenced)]
+    code_block: CodeBlock,
+
+    /// 強調の記号（asterisk: `*em*` / `**strong**`、underscore: `_em_` / `__strong__`）
+    #[arg(long, value_enum, default_value_t = Emphasis::Asterisk)]
+    emphasis: Emphasis,
+
+    /// 改行（`<br>`）の書式（spaces: 行末の 2 つの空白、backslash: 行末の `\`）
+    #[arg(long, value_enum, default_value_t = LineBreak::Spaces)]
+    line_break: LineBreak,
+
+    /// リンクの書式（inline / reference / footnote）。
+    /// reference と footnote は URL を番号付きの定義として末尾（断片ごとの出力では断片の末尾）にまとめる。
+    #[arg(long, value_enum, default_value_t = LinkStyle::Inline)]
+    link_style: LinkStyle,
+
+    /// --link-style reference / footnote で画像も参照形式にする
+    #[arg(long)]
+    reference_images: bool,
+
+    /// リンク先 URL の扱い（absolute: 絶対 URL にする、keep: 書かれたまま残す、
+    /// relative-to: --link-base と同じオリジンの URL をそこからの相対 URL にする）
+    #[arg(long, value_enum, default_value_t = LinkMode::Absolute)]
+    links: LinkMode,
+
+    /// --links relative-to の基準 URL（例: 'https://docs.vendor.com/guide/'）
+    #[arg(long, value_name = "URL")]
+    link_base: Option<Url>,
+
+    /// リンク先 URL の書き換え規則（例: 'https://docs\.vendor\.com/(.*)=>./vendor/$1.md'、複数指定可）。
+    /// 解決後の絶対 URL に順に試し、最初に一致した規則の置換結果をそのまま使う。
+    #[arg(long, value_name = "REGEX=>REPLACEMENT")]
+    rewrite_link: Vec<RewriteRule>,
+
+    /// リンク・画像の URL からトラッキング用のクエリパラメータ（utm_*・fbclid・gclid・セッション ID など）を
+    /// 取り除き、既定のポートを省いてパーセントエンコーディングを正規化する
+    #[arg(long)]
+    clean_urls: bool,
+
+    /// --clean-urls で既定に加えて取り除くクエリパラメータ（`*` で終わると前方一致、複数指定可）
+    #[arg(long, value_name = "NAME")]
+    tracking_param: Vec<String>,
+
+    /// 画像の扱い（keep: URL のまま、strip: 代替テキストだけを残す、
+    /// inline-data-uri: 取得して `data:` URI として埋め込む）
+    #[arg(long, value_enum, default_value_t = ImageMode::Keep)]
+    images: ImageMode,
+
+    /// 画像をブラウザのセッション（Cookie 付き）で取得してディレクトリに保存し、
+    /// 画像のリンク先を出力ファイル（標準出力ならカレントディレクトリ）からの相対パスにする。
+    /// ファイル名は内容のハッシュと拡張子。
+    #[arg(long, value_name = "DIR")]
+    download_images: Option<PathBuf>,
+
+    /// srcset / `<picture>` の候補から、この幅（px）以上で最小の画像を選ぶ（未指定なら最大の画像）
+    #[arg(long, value_name = "PX")]
+    image_width: Option<u32>,
+
+    /// `data-src` などの遅延読み込み属性を src に昇格させず、srcset の候補も選ばない
+    #[arg(long)]
+    no_lazy_images: bool,
+
+    /// コードブロックの行番号・コピーボタン・選択できない要素（プロンプト・差分記号など）を取り除かない
+    #[arg(long)]
+    no_clean_code: bool,
+
+    /// コードブロックのノイズ除去で `$` などのプロンプトは残す
+    #[arg(long)]
+    keep_prompts: bool,
+
+    /// 変換時に内容ごと取り除くタグ（既定の script / style / noscript / svg に追加、複数指定可）
+    #[arg(long, value_name = "TAG")]
+    skip_tag: Vec<String>,
+
+    /// 既定で取り除くタグのうち変換に含めるもの（複数指定可）
+    #[arg(long, value_name = "TAG")]
+    keep_tag: Vec<String>,
+
+    /// 出力形式（markdown / text / html / json / ndjson）。
+    /// text は Markdown の記法を除いたテキスト、html はスクリプトを除き URL を解決した HTML 断片、
+    /// json はページごとの URL・タイトル・メタデータ・セレクタごとの断片・警告・処理時間を出力する。
+    #[arg(long, value_enum, default_value_t = OutputFormat::Markdown)]
+    format: OutputFormat,
+
+    /// json / ndjson 出力の断片に変換前の HTML も含める
+    #[arg(long)]
+    include_html: bool,
+
     /// 出力ファイルパス。省略時は標準出力へ書き込む。
     #[arg(short, long)]
     output: Option<PathBuf>,
@@ -44,6 +218,44 @@ struct Cli {
     #[arg(short, long, default_value_t = 60)]
     timeout: u64,
 
+    /// 一致しないセレクタが 1 つでもあればエラーにする（終了コード 3）
+    #[arg(long)]
+    strict: bool,
+
+    /// セレクタの最小一致件数（例: 'article h2>=3'、複数指定可）。
+    /// 満たさなければエラーにする（終了コード 3）。
+    #[arg(long, value_name = "SELECTOR>=N")]
+    expect: Vec<Expectation>,
+
+    /// 複数セレクタの一致結果の出力順（selector: 指定順、document: ページ上の出現順）。
+    /// 先の一致要素（前のセレクタの一致など）の内側にある重複は常に取り除く。
+    #[arg(long, value_enum, default_value_t = Order::Selector)]
+    order: Order,
+
+    /// オープンなシャドウルートと iframe の中も検索し、シャドウ DOM を展開して出力する。
+    /// クロスオリジンの iframe には CDP のフレームターゲット経由でアクセスする。
+    #[arg(long)]
+    pierce: bool,
+
+    /// リクエストに付加する HTTP ヘッダー（例: 'Authorization: Bearer xxx'、複数指定可）。URL と同じオリジンへのリクエストにだけ付ける
+    #[arg(short = 'H', long, value_name = "NAME: VALUE")]
+    header: Vec<Header>,
+
+    /// 設定ファイルとコマンドライン引数を統合した、URL に適用される設定を表示して終了する。
+    /// `Authorization`・`Cookie` などの認証情報のヘッダー値は伏せる。
+    #[arg(long)]
+    print_config: bool,
+
+    /// サイトが公開している Markdown 版（`<link rel="alternate" type="text/markdown">`、
+    /// `.md` 版、サイトトップの `llms.txt`）があれば、HTML を変換せずにそれを出力する
+    #[arg(long)]
+    prefer_source: bool,
+
+    /// GitHub・Wikipedia・Stack Exchange 向けのサイトアダプタを使わない。
+    /// --selector / --xpath / --preset 指定時はアダプタを使わない。
+    #[arg(long)]
+    no_adapters: bool,
+
     /// ブラウザウィンドウを表示する（デバッグ用）
     #[arg(long)]
     no_headless: bool,
@@ -57,28 +269,353 @@ struct Cli {
     quiet: bool,
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-    let mut progress = Progress::new(!cli.quiet);
+impl Cli {
+    /// 設定ファイルの値を、コマンドラインで明示されていない項目に適用する。
+    /// ヘッダーはヘッダー名ごとにコマンドライン側を優先して統合する。
+    fn apply_settings(&mut self, matches: &ArgMatches, settings: &Settings) {
+        let explicit = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
 
-    let selectors = if cli.selector.is_empty() {
-        vec!["body".to_string()]
-    } else {
-        cli.selector
-    };
+        // 変換対象はセレクタと XPath をまとめて 1 つの指定として扱う
+        if !explicit("selector") && !explicit("xpath") {
+            if let Some(selector) = &settings.selector {
+                self.selector = selector.clone();
+            }
+            if let Some(xpath) = &settings.xpath {
+                self.xpath = xpath.clone();
+            }
+        }
+        macro_rules! fill {
+            ($($field:ident),*) => {
+                $(if !explicit(stringify!($field)) {
+                    if let Some(value) = &settings.$field {
+                        self.$field = value.clone();
+                    }
+                })*
+            };
+        }
+        fill!(
+            exclude,
+            wait,
+            timeout,
+            heading,
+            bullet,
+            code_block,
+            emphasis,
+            line_break,
+            link_style,
+            reference_images,
+            links,
+            rewrite_link,
+            clean_urls,
+            tracking_param,
+            images,
+            no_lazy_images,
+            no_clean_code,
+            keep_prompts,
+            skip_tag,
+            keep_tag,
+            order,
+            pierce,
+            strict,
+            prefer_source,
+            no_cache
+        );
+        if !explicit("preset") && settings.preset.is_some() {
+            self.preset = settings.preset;
+        }
+        if !explicit("front_matter") && settings.front_matter.is_some() {
+            self.front_matter = settings.front_matter;
+        }
+        if !explicit("download_images") && settings.download_images.is_some() {
+            self.download_images = settings.download_images.clone();
+        }
+        if !explicit("image_width") && settings.image_width.is_some() {
+            self.image_width = settings.image_width;
+        }
+        if !explicit("link_base") && settings.link_base.is_some() {
+            self.link_base = settings.link_base.clone();
+        }
+        if !explicit("output") && settings.output.is_some() {
+            self.output = settings.output.clone();
+        }
+
+        if let Some(headers) = &settings.headers {
+            let mut merged: Vec<Header> = headers
+                .iter()
+                .filter(|(name, _)| {
+                    !self
+                        .header
+                        .iter()
+                        .any(|h| h.name.eq_ignore_ascii_case(name))
+                })
+                .map(|(name, value)| Header {
+                    name: name.clone(),
+                    value: value.clone(),
+                })
+                .collect();
+            merged.append(&mut self.header);
+            self.header = merged;
+        }
+    }
+
+    /// HTML から Markdown への変換の書式
+    fn convert_options(&self) -> ConvertOptions {
+        ConvertOptions {
+            heading: self.heading,
+            bullet: self.bullet,
+            code_block: self.code_block,
+            emphasis: self.emphasis,
+            line_break: self.line_break,
+            skip_tags: self.skip_tag.clone(),
+            keep_tags: self.keep_tag.clone(),
+        }
+    }
+
+    /// 抽出した要素の複製に加える処理（汎用の抽出とサイトアダプタで共通）
+    fn extract_options(&self) -> ExtractOptions {
+        ExtractOptions {
+            pierce: self.pierce,
+            exclude: self.exclude.clone(),
+            lazy_images: !self.no_lazy_images,
+            image_width: self.image_width,
+            clean_code: !self.no_clean_code,
+            keep_prompts: self.keep_prompts,
+            math: true,
+        }
+    }
+
+    /// 実際に使われる設定値
+    fn effective_settings(&self) -> Settings {
+        Settings {
+            selector: Some(self.selector.clone()),
+            xpath: Some(self.xpath.clone()),
+            preset: self.preset,
+            exclude: Some(self.exclude.clone()),
+            wait: Some(self.wait),
+            timeout: Some(self.timeout),
+            headers: Some(
+                self.header
+                    .iter()
+                    .map(|h| (h.name.clone(), h.value.clone()))
+                    .collect(),
+            ),
+            front_matter: self.front_matter,
+            heading: Some(self.heading),
+            bullet: Some(self.bullet),
+            code_block: Some(self.code_block),
+            emphasis: Some(self.emphasis),
+            line_break: Some(self.line_break),
+            link_style: Some(self.link_style),
+            reference_images: Some(self.reference_images),
+            links: Some(self.links),
+            link_base: self.link_base.clone(),
+            rewrite_link: Some(self.rewrite_link.clone()),
+            clean_urls: Some(self.clean_urls),
+            tracking_param: Some(self.tracking_param.clone()),
+            images: Some(self.images),
+            download_images: self.download_images.clone(),
+            image_width: self.image_width,
+            no_lazy_images: Some(self.no_lazy_images),
+            no_clean_code: Some(self.no_clean_code),
+            keep_prompts: Some(self.keep_prompts),
+            skip_tag: Some(self.skip_tag.clone()),
+            keep_tag: Some(self.keep_tag.clone()),
+            output: self.output.clone(),
+            order: Some(self.order),
+            pierce: Some(self.pierce),
+            strict: Some(self.strict),
+            prefer_source: Some(self.prefer_source),
+            no_cache: Some(self.no_cache),
+            ..Settings::default()
+        }
+    }
+}
+
+/// `--strict` / `--expect` の条件を満たさなかった場合の終了コード
+const EXIT_SELECTOR_MISMATCH: u8 = 3;
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            if e.is::<SelectorMismatch>() {
+                ExitCode::from(EXIT_SELECTOR_MISMATCH)
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+    }
+}
+
+fn run() -> Result<()> {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    // URL ごとに設定ファイルの既定値と一致するプロファイルを適用する
+    let config = Config::load()?;
+    let mut pages = Vec::new();
+    for (i, url) in cli.urls.iter().enumerate() {
+        let resolved = config.resolve(url);
+        let mut page = cli.clone();
+        page.apply_settings(&matches, &resolved.settings);
+        if cli.print_config {
+            if i > 0 {
+                println!();
+            }
+            println!("# Effective settings for {url}");
+            for source in &resolved.sources {
+                println!("# from {source}");
+            }
+            let mut settings = page.effective_settings();
+            settings.mask_secrets();
+            print!(
+                "{}",
+                toml::to_string_pretty(&settings).context("Failed to serialize settings")?
+            );
+        }
+        pages.push((url.as_str(), page));
+    }
+    if cli.print_config {
+        return Ok(());
+    }
+
+    for (_, page) in &pages {
+        if page.links == LinkMode::RelativeTo && page.link_base.is_none() {
+            bail!("--links relative-to requires --link-base");
+        }
+        if page.download_images.is_some() && page.images != ImageMode::Keep {
+            bail!("--download-images cannot be combined with --images strip / inline-data-uri");
+        }
+        if let Some(unknown) = find_unknown_expectation(&selector_specs(page), &page.expect) {
+            bail!(
+                "--expect refers to selector '{}' which is not given with --selector or --xpath",
+                unknown.selector
+            );
+        }
+    }
+    // 出力先は最初の URL の設定に従う
+    let output = pages.first().and_then(|(_, page)| page.output.clone());
+
+    let mut progress = Progress::new(!cli.quiet);
 
     // ブラウザを起動する
     progress.spinner("Launching Chrome...");
+    let timeout = pages
+        .iter()
+        .map(|(_, page)| page.timeout)
+        .max()
+        .unwrap_or(cli.timeout);
     let launch_options = LaunchOptions {
         headless: !cli.no_headless,
-        path: cli.chrome_path,
-        idle_browser_timeout: idle_browser_timeout(cli.timeout),
+        path: cli.chrome_path.clone(),
+        idle_browser_timeout: idle_browser_timeout(timeout),
         ..LaunchOptions::default()
     };
 
     let browser = Browser::new(launch_options)
         .context("Failed to launch Chrome. Make sure Chrome is installed on your system")?;
+    progress.finish("Chrome launched");
+
+    // 複数ページでは失敗したページを記録して残りのページを続け、最後にエラーを返す
+    let mut reports = Vec::new();
+    let mut failures = Vec::new();
+    for (url, page) in &pages {
+        match process_page(&browser, url, page, &mut progress) {
+            Ok(report) => reports.push(report),
+            Err(e) if pages.len() > 1 => {
+                progress.finish_and_clear();
+                eprintln!("Error: {url}: {e:#}");
+                reports.push(PageReport {
+                    url: url.to_string(),
+                    warnings: progress.take_warnings(),
+                    error: Some(format!("{e:#}")),
+                    ..Default::default()
+                });
+                failures.push(e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    let rendered = render(cli.format, &reports)?;
+
+    // 出力内容を確定する（末尾改行を保証）
+    let output_bytes = if output.is_some() && !rendered.ends_with('\n') {
+        format!("{rendered}\n")
+    } else {
+        rendered
+    };
+
+    // 出力
+    let old_content = output.as_ref().and_then(|p| std::fs::read(p).ok());
+    let mut writer: Box<dyn Write> = match &output {
+        Some(path) => {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent).with_context(|| {
+                    format!("Failed to create output directory: {}", parent.display())
+                })?;
+            }
+            let file = File::create(path)
+                .with_context(|| format!("Failed to create output file: {}", path.display()))?;
+            Box::new(file)
+        }
+        None => Box::new(io::stdout().lock()),
+    };
+
+    writer
+        .write_all(output_bytes.as_bytes())
+        .context("Failed to write output")?;
+
+    // 出力成功後にのみ URL 付きの完了表示を行う
+    match &output {
+        Some(path) => {
+            let (icon, status) = file_status(path, &old_content, output_bytes.as_bytes());
+            let label = match reports.as_slice() {
+                [report] => page_label(report),
+                reports => format!("{} pages", reports.len()),
+            };
+            progress.complete(icon, &format!("{label} → {} ({status})", path.display()));
+        }
+        None => {
+            for report in &reports {
+                let icon = if report.error.is_some() { "✘" } else { "✔" };
+                progress.complete(icon, &page_label(report));
+            }
+        }
+    }
+
+    match failures.len() {
+        0 => Ok(()),
+        failed => {
+            // 終了コードは最初の失敗に従う（`--strict` の不一致なら 3）
+            let first = failures.swap_remove(0);
+            Err(first.context(format!("{failed} of {} pages failed", pages.len())))
+        }
+    }
+}
 
+/// `-s` / `--xpath` の指定（省略時は body）
+fn selector_specs(cli: &Cli) -> Vec<SelectorSpec> {
+    let mut specs: Vec<SelectorSpec> = cli
+        .selector
+        .iter()
+        .map(|s| SelectorSpec::parse(s))
+        .chain(cli.xpath.iter().map(|x| SelectorSpec::parse_xpath(x)))
+        .collect();
+    if specs.is_empty() {
+        specs.push(SelectorSpec::parse("body"));
+    }
+    specs
+}
+
+/// 新しいタブで 1 ページを読み込み、Markdown に変換した結果を返す
+fn process_page(
+    browser: &Browser,
+    url: &str,
+    cli: &Cli,
+    progress: &mut Progress,
+) -> Result<PageReport> {
+    let started = Instant::now();
     let tab = browser.new_tab().context("Failed to open new tab")?;
     tab.set_default_timeout(Duration::from_secs(cli.timeout));
     if cli.no_cache {
@@ -87,12 +624,14 @@ fn main() -> Result<()> {
         })
         .context("Failed to disable browser cache")?;
     }
-    progress.finish("Chrome launched");
+    if !cli.header.is_empty() {
+        scope_headers(&tab, url, &cli.header)?;
+    }
 
     // ページへ遷移する
-    progress.spinner(&format!("Loading page: {}", cli.url));
-    tab.navigate_to(&cli.url)
-        .with_context(|| format!("Failed to navigate to URL: {}", cli.url))?;
+    progress.spinner(&format!("Loading page: {url}"));
+    tab.navigate_to(url)
+        .with_context(|| format!("Failed to navigate to URL: {url}"))?;
 
     tab.wait_until_navigated().context("Page load timed out")?;
 
@@ -102,107 +641,605 @@ fn main() -> Result<()> {
         std::thread::sleep(Duration::from_secs(cli.wait));
     }
     progress.finish("Page loaded");
+    let load_ms = elapsed_ms(started);
+
+    // 相対 URL はリダイレクト後の URL と `<base href>` を反映した document.baseURI で解決する
+    let final_url = tab.get_url();
+    let base_url = document_base_url(&tab).unwrap_or_else(|| final_url.clone());
+
+    // 抽出前のページからフロントマター・JSON 出力用のメタデータを集める
+    let front_matter = match cli.format {
+        OutputFormat::Text | OutputFormat::Html => {
+            if cli.front_matter.is_some() {
+                progress.warn("front matter is only added to Markdown and JSON output");
+            }
+            None
+        }
+        OutputFormat::Markdown | OutputFormat::Json | OutputFormat::Ndjson => cli.front_matter,
+    };
+    let json = matches!(cli.format, OutputFormat::Json | OutputFormat::Ndjson);
+    let metadata = if front_matter.is_some() || json {
+        Some(collect_metadata(&tab, url)?)
+    } else {
+        None
+    };
 
+    // HTML 以外の文書（テキスト・Markdown・JSON）は変換せずにそのまま出力する
+    let converting = Instant::now();
+    let content = match read_plain_document(&tab)? {
+        Some((content_type, kind, body)) => {
+            if !cli.selector.is_empty() || !cli.xpath.is_empty() {
+                progress.warn(format!("selectors are ignored for {content_type} content"));
+            }
+            progress.complete("✔", &format!("Passed through {content_type} content"));
+            PageContent {
+                markdown: body,
+                html: None,
+                selectors: Vec::new(),
+                plain: Some(kind),
+            }
+        }
+        None => {
+            let mut images = ImageStore::new(
+                &tab,
+                cli.images,
+                cli.download_images.as_deref(),
+                cli.output.as_deref(),
+            );
+            let content = page_markdown(&tab, cli, url, &base_url, images.as_mut(), progress)?;
+            for warning in images.iter_mut().flat_map(ImageStore::take_warnings) {
+                progress.warn(warning);
+            }
+            content
+        }
+    };
+
+    // text / html 出力はテキストと同じ抽出結果から作る
+    let body = match cli.format {
+        OutputFormat::Text => text_body(&content.markdown, content.plain),
+        OutputFormat::Html if content.plain.is_some() => preformatted(&content.markdown),
+        OutputFormat::Html => {
+            // Markdown 版に含まれる生の HTML もページの断片と同じく整形する
+            let fragments = match &content.html {
+                Some(fragments) => fragments.clone(),
+                None => vec![markdown_to_html(&content.markdown)],
+            };
+            clean_html(&tab, &fragments)?.join(html::FRAGMENT_SEPARATOR)
+        }
+        OutputFormat::Markdown | OutputFormat::Json | OutputFormat::Ndjson => String::new(),
+    };
+    let markdown = match (front_matter, &metadata) {
+        (Some(format), Some(metadata)) => {
+            format!(
+                "{}\n{}",
+                metadata.to_front_matter(format)?,
+                content.markdown
+            )
+        }
+        _ => content.markdown,
+    };
+    progress.finish("Converted to Markdown");
+    let convert_ms = elapsed_ms(converting);
+
+    // 後続のページに影響しないようタブを閉じる（失敗しても結果には影響しない）
+    let _ = tab.close(true);
+
+    Ok(PageReport {
+        url: url.to_string(),
+        final_url: Some(final_url),
+        title: metadata.as_ref().and_then(|m| m.title.clone()),
+        metadata,
+        markdown,
+        body,
+        selectors: content.selectors,
+        warnings: progress.take_warnings(),
+        timings: Timings {
+            load_ms,
+            convert_ms,
+            total_ms: elapsed_ms(started),
+        },
+        error: None,
+    })
+}
+
+/// `-H` / プロファイルのヘッダーを、`url` と同じオリジンへのリクエストにだけ付ける。
+///
+/// `Network.setExtraHTTPHeaders` はタブのすべてのリクエストに付くため、認証情報が
+/// CDN や解析スクリプトなど他のオリジンに送られてしまう。Fetch ドメインで同じオリジンへの
+/// リクエストだけを一時停止し、ヘッダーを足して続行させる。
+fn scope_headers(tab: &Arc<Tab>, url: &str, headers: &[Header]) -> Result<()> {
+    let origin = Url::parse(url)
+        .with_context(|| format!("Invalid URL: {url}"))?
+        .origin();
+    let pattern = Fetch::RequestPattern {
+        url_pattern: Some(format!("{}/*", origin.ascii_serialization())),
+        resource_Type: None,
+        request_stage: Some(Fetch::RequestStage::Request),
+    };
+    tab.enable_fetch(Some(&[pattern]), None)
+        .context("Failed to enable request interception")?;
+    let headers = headers.to_vec();
+    tab.enable_request_interception(Arc::new(
+        move |_transport, _session_id, event: RequestPausedEvent| {
+            let request = event.params.request;
+            RequestPausedDecision::Continue(Some(Fetch::ContinueRequest {
+                request_id: event.params.request_id,
+                url: None,
+                method: None,
+                post_data: None,
+                headers: request_headers(&request.url, &request.headers, &origin, &headers),
+                intercept_response: None,
+            }))
+        },
+    ))
+    .context("Failed to enable request interception")
+}
+
+/// `request_url` へのリクエストのヘッダー（ブラウザが付けた `existing` に `headers` を足したもの）。
+/// `origin` と異なるオリジンなら `None`（ヘッダーを変えない）を返す。
+fn request_headers(
+    request_url: &str,
+    existing: &Network::Headers,
+    origin: &Origin,
+    headers: &[Header],
+) -> Option<Vec<Fetch::HeaderEntry>> {
+    if Url::parse(request_url).ok()?.origin() != *origin {
+        return None;
+    }
+    let existing = existing.0.as_ref().and_then(|v| v.as_object());
+    let kept = existing.into_iter().flatten().filter_map(|(name, value)| {
+        let overridden = headers.iter().any(|h| h.name.eq_ignore_ascii_case(name));
+        (!overridden).then(|| Fetch::HeaderEntry {
+            name: name.clone(),
+            value: value.as_str().unwrap_or_default().to_string(),
+        })
+    });
+    let added = headers.iter().map(|h| Fetch::HeaderEntry {
+        name: h.name.clone(),
+        value: h.value.clone(),
+    });
+    Some(kept.chain(added).collect())
+}
+
+/// `--format text` の本文。Markdown（変換結果・Markdown 文書）はテキストにし、
+/// プレーンテキスト・JSON の文書はそのまま出力する。
+fn text_body(markdown: &str, plain: Option<PlainKind>) -> String {
+    match plain {
+        Some(PlainKind::Text | PlainKind::Json) => markdown.to_string(),
+        Some(PlainKind::Markdown) | None => markdown_to_text(markdown),
+    }
+}
+
+/// ページの document.baseURI（取得できなければ `None`）
+fn document_base_url(tab: &Tab) -> Option<String> {
+    let result = tab.evaluate("document.baseURI", false).ok()?;
+    result
+        .value
+        .as_ref()
+        .and_then(|v| v.as_str())
+        .filter(|base| Url::parse(base).is_ok())
+        .map(String::from)
+}
+
+/// 完了表示のラベル（リダイレクトされた場合は最終的な URL も示す）
+fn page_label(report: &PageReport) -> String {
+    match &report.final_url {
+        Some(final_url)
+            if !final_url.is_empty()
+                && Url::parse(final_url).ok() != Url::parse(&report.url).ok() =>
+        {
+            format!("{} → {final_url}", report.url)
+        }
+        _ => report.url.clone(),
+    }
+}
+
+fn elapsed_ms(since: Instant) -> u64 {
+    u64::try_from(since.elapsed().as_millis()).unwrap_or(u64::MAX)
+}
+
+/// ページから取り出した内容
+struct PageContent {
+    /// 相対 URL を解決した Markdown（フロントマターは含まない）
+    markdown: String,
+    /// 変換元の HTML 断片（サイトの Markdown 版・テキスト文書では `None`）
+    html: Option<Vec<String>>,
+    /// セレクタごとの一致結果
+    selectors: Vec<SelectorReport>,
+    /// 変換せずに出力する文書の種類（HTML ページなら `None`）
+    plain: Option<PlainKind>,
+}
+
+/// HTML ページから Markdown を作る。
+///
+/// サイトが公開している Markdown 版（`--prefer-source`）、サイトアダプタ、
+/// セレクタによる抽出と変換の順に試し、相対 URL を `base_url` で解決して返す。
+/// セレクタで抽出した場合はセレクタごとの一致結果も返す。
+fn page_markdown(
+    tab: &Tab,
+    cli: &Cli,
+    url: &str,
+    base_url: &str,
+    mut images: Option<&mut ImageStore>,
+    progress: &mut Progress,
+) -> Result<PageContent> {
+    // サイトが公開している Markdown 版、次いでサイトアダプタを汎用の抽出より優先する
+    let use_adapters =
+        !cli.no_adapters && cli.selector.is_empty() && cli.xpath.is_empty() && cli.preset.is_none();
+    let mut options = cli.extract_options();
+    let mut adapted = None;
+    if cli.prefer_source {
+        adapted = find_markdown_source(tab, url, progress);
+    }
+    if adapted.is_none() && use_adapters {
+        adapted = run_adapter(tab, url, &options, progress);
+    }
+
+    match adapted {
+        Some(Extracted::Markdown {
+            mut text,
+            base_url: source_url,
+            image_base_url,
+        }) => {
+            if let Some(image_base_url) = image_base_url {
+                text = resolve_markdown_images(&text, &image_base_url);
+            }
+            Ok(PageContent {
+                markdown: finish_markdown(&text, &source_url, cli, images),
+                html: None,
+                selectors: Vec::new(),
+                plain: None,
+            })
+        }
+        Some(Extracted::Html(html_fragments)) => {
+            progress.spinner("Converting to Markdown...");
+            let markdown = convert_fragments(&html_fragments, &cli.convert_options())?;
+            Ok(PageContent {
+                markdown: finish_markdown(&markdown, base_url, cli, images.as_deref_mut()),
+                html: Some(html_fragments),
+                selectors: Vec::new(),
+                plain: None,
+            })
+        }
+        None => {
+            let mut specs = selector_specs(cli);
+            let nth = if cli.first {
+                Some(1)
+            } else {
+                cli.nth.map(|n| n as usize)
+            };
+            if let Some(preset) = cli.preset {
+                apply_preset(tab, cli, preset, &mut specs, &mut options.exclude, progress)?;
+            }
+            let extraction = extract_fragments(tab, cli, &specs, nth, &options, progress)?;
+
+            // HTML を Markdown に変換する
+            progress.spinner("Converting to Markdown...");
+            let html_fragments: Vec<String> = extraction
+                .fragments
+                .iter()
+                .map(|(_, html)| html.clone())
+                .collect();
+            let parts = convert_each(&html_fragments, &cli.convert_options())?;
+            let markdown = compact_markdown(&parts.join(FRAGMENT_SEPARATOR));
+
+            // 断片ごとの Markdown は JSON 出力にしか含めないため、それ以外では作らない
+            let json = matches!(cli.format, OutputFormat::Json | OutputFormat::Ndjson);
+            let selectors = specs
+                .iter()
+                .enumerate()
+                .map(|(i, spec)| SelectorReport {
+                    selector: spec.to_string(),
+                    matched: extraction.matched[i].clone(),
+                    count: extraction.counts[i],
+                    fragments: extraction
+                        .fragments
+                        .iter()
+                        .zip(&parts)
+                        .filter(|((selector_idx, _), _)| json && *selector_idx == i)
+                        .map(|((_, html), md)| FragmentReport {
+                            markdown: finish_markdown(
+                                &compact_markdown(md),
+                                base_url,
+                                cli,
+                                images.as_deref_mut(),
+                            ),
+                            html: cli.include_html.then(|| html.clone()),
+                        })
+                        .collect(),
+                })
+                .collect();
+            Ok(PageContent {
+                markdown: finish_markdown(&markdown, base_url, cli, images),
+                html: Some(html_fragments),
+                selectors,
+                plain: None,
+            })
+        }
+    }
+}
+
+/// 画像を取得・保存し（`images` 指定時）、相対 URL を解決・書き換え、
+/// `--link-style` に従ってリンクの書式を整える
+fn finish_markdown(md: &str, base_url: &str, cli: &Cli, images: Option<&mut ImageStore>) -> String {
+    // 画像は `--links` や書き換え規則を適用する前の、ページ URL で解決した URL から取得する
+    let mut saved = HashSet::new();
+    let md = match images {
+        Some(images) => rewrite_markdown_images(md, &mut |url| {
+            let action = images.action(url, base_url);
+            if let ImageAction::Replace(new) = &action {
+                saved.insert(new.clone());
+            }
+            action
+        }),
+        None => md.to_string(),
+    };
+    // 既定（絶対 URL・書き換え規則なし）はページ URL での解決だけを行う
+    let default_links =
+        cli.links == LinkMode::Absolute && cli.rewrite_link.is_empty() && !cli.clean_urls;
+    let md = if default_links && saved.is_empty() {
+        resolve_markdown_urls(&md, base_url)
+    } else {
+        let cleaner = cli.clean_urls.then(|| UrlCleaner::new(&cli.tracking_param));
+        let links = LinkRewriter::new(
+            base_url,
+            cli.links,
+            cli.link_base.as_ref(),
+            &cli.rewrite_link,
+            cleaner.as_ref(),
+        );
+        rewrite_markdown_links(&md, &links, &saved)
+    };
+    restyle_links(&md, cli.link_style, cli.reference_images)
+}
+
+/// サイトが公開している Markdown 版（alternate リンク・`.md` 版・`llms.txt`）を取得する。
+/// 見つからなければ警告して `None` を返し、HTML の変換に任せる。
+fn find_markdown_source(tab: &Tab, url: &str, progress: &mut Progress) -> Option<Extracted> {
+    progress.spinner("Looking for a Markdown source...");
+    let result = fetch_source(tab, url);
+    progress.finish_and_clear();
+    match result {
+        Ok(Some(source)) => {
+            progress.complete("✔", &format!("Source: {}", source.url));
+            Some(Extracted::Markdown {
+                text: source.text,
+                base_url: source.url,
+                image_base_url: None,
+            })
+        }
+        Ok(None) => {
+            progress.warn("no Markdown source found, converting the HTML page");
+            None
+        }
+        Err(e) => {
+            progress.warn(format!(
+                "failed to fetch Markdown source, converting the HTML page: {e:#}"
+            ));
+            None
+        }
+    }
+}
+
+/// URL に対応するサイトアダプタで内容を取り出す。
+/// 対応するアダプタがない・失敗した場合は `None` を返し、汎用の抽出に任せる。
+fn run_adapter(
+    tab: &Tab,
+    url: &str,
+    options: &ExtractOptions,
+    progress: &mut Progress,
+) -> Option<Extracted> {
+    let (adapter, url) = find_adapter(url)?;
+    progress.spinner(&format!("Extracting with {} adapter...", adapter.name()));
+    let result = adapter.extract(tab, &url, options);
+    progress.finish_and_clear();
+    match result {
+        Ok(Some(extracted)) => {
+            progress.complete("✔", &format!("Adapter: {}", adapter.name()));
+            Some(extracted)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            progress.warn(format!(
+                "{} adapter failed, using default extraction: {e:#}",
+                adapter.name()
+            ));
+            None
+        }
+    }
+}
+
+/// プリセットの待機条件を待ち、本文セレクタ（`--selector` / `--xpath` 未指定時）と
+/// 除外セレクタを適用する。`auto` ならページから生成ツールを判定する。
+fn apply_preset(
+    tab: &Tab,
+    cli: &Cli,
+    choice: Preset,
+    specs: &mut Vec<SelectorSpec>,
+    exclude: &mut Vec<String>,
+    progress: &mut Progress,
+) -> Result<()> {
+    let preset = match choice {
+        Preset::Auto => {
+            progress.spinner("Detecting documentation framework...");
+            let signals = tab
+                .evaluate(&detection_script(), false)
+                .context("Failed to detect documentation framework")?;
+            progress.finish_and_clear();
+            detect(&parse_signals(signals.value.as_ref())?)
+        }
+        preset => Some(preset),
+    };
+    match preset.and_then(|p| p.rules().map(|rules| (p, rules))) {
+        Some((preset, rules)) => {
+            if let Some(selector) = rules.wait_for {
+                progress.spinner(&format!("Waiting for '{selector}'..."));
+                let waited = tab.wait_for_element_with_custom_timeout(
+                    selector,
+                    Duration::from_secs(cli.timeout),
+                );
+                progress.finish_and_clear();
+                if waited.is_err() {
+                    progress.warn(format!(
+                        "element '{selector}' expected by preset '{preset}' did not appear"
+                    ));
+                }
+            }
+            if cli.selector.is_empty() && cli.xpath.is_empty() {
+                *specs = vec![SelectorSpec::parse(rules.selector)];
+            }
+            exclude.extend(rules.exclude.iter().map(ToString::to_string));
+            progress.complete("✔", &format!("Preset: {preset}"));
+        }
+        None => {
+            progress.warn("no known documentation framework detected, using default extraction")
+        }
+    }
+    Ok(())
+}
+
+/// セレクタによる抽出結果
+struct Extraction {
+    /// 重複を除いて指定順に並べた HTML 断片と、その断片を得たセレクタの番号
+    fragments: Vec<(usize, String)>,
+    /// セレクタごとに採用した代替セレクタ（一致しなければ `None`）
+    matched: Vec<Option<String>>,
+    /// セレクタごとの一致件数
+    counts: Vec<usize>,
+}
+
+/// 各セレクタの一致要素を抽出し、重複を除いて指定順に並べた HTML 断片を返す
+fn extract_fragments(
+    tab: &Tab,
+    cli: &Cli,
+    specs: &[SelectorSpec],
+    nth: Option<usize>,
+    options: &ExtractOptions,
+    progress: &mut Progress,
+) -> Result<Extraction> {
     // セレクタに一致した要素の HTML を抽出する
     progress.spinner("Extracting HTML elements...");
-    let mut html_fragments = Vec::new();
-    for selector in &selectors {
-        progress.set_message(&format!("Extracting selector '{}'...", selector));
-
-        // 一致した全要素の outerHTML を取得する
-        let js = format!(
-            r#"(() => {{
-                const els = document.querySelectorAll({selector});
-                return Array.from(els).map(el => el.outerHTML).join('\n');
-            }})()"#,
-            selector = escape_js_string(selector),
-        );
-
-        let result = tab
-            .evaluate(&js, false)
-            .with_context(|| format!("Failed to evaluate selector '{}'", selector))?;
-
-        let html = result
-            .value
-            .as_ref()
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .to_string();
-
-        if html.is_empty() {
-            eprintln!("Warning: no elements matched selector '{}'", selector);
-        } else {
-            html_fragments.push(html);
+    let frames = if cli.pierce {
+        match FrameTargets::attach(tab, Duration::from_secs(cli.timeout)) {
+            Ok(frames) => Some(frames),
+            Err(e) => {
+                progress.warn(format!("failed to attach to cross-origin frames: {e:#}"));
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let mut matches = Vec::new();
+    let mut match_counts = Vec::new();
+    let mut matched = Vec::new();
+    let mut fallback_reports = Vec::new();
+    for spec in specs {
+        // 代替セレクタを先頭から評価し、最初に一致したものを採用する
+        let mut spec_matches = Vec::new();
+        let mut matched_selector = None;
+        for selector in &spec.alternatives {
+            progress.set_message(&format!("Extracting selector '{}'...", selector));
+            spec_matches = evaluate_selector(tab, frames.as_ref(), selector, options, progress)?;
+            if !spec_matches.is_empty() {
+                matched_selector = Some(selector.to_string());
+                if spec.has_fallback() {
+                    fallback_reports.push(format!("'{}' matched '{}'", spec, selector));
+                }
+                break;
+            }
+        }
+
+        if spec_matches.is_empty() && !cli.strict {
+            progress.warn(format!("no elements matched selector '{}'", spec));
         }
+        match_counts.push(spec_matches.len());
+        matched.push(matched_selector);
+        matches.push(select_nth(spec_matches, nth));
     }
+    drop(frames);
     progress.finish_and_clear();
+    for report in &fallback_reports {
+        progress.complete("↪", report);
+    }
+    check_match_counts(specs, &match_counts, cli.strict, &cli.expect)?;
 
-    if html_fragments.is_empty() {
+    // 他の一致の内側にある重複を除き、指定順に並べる
+    let fragments = arrange_fragments(&matches, cli.order);
+    if fragments.is_empty() {
         bail!("No elements matched the specified selectors");
     }
+    Ok(Extraction {
+        fragments,
+        matched,
+        counts: match_counts,
+    })
+}
 
-    // HTML を Markdown に変換する
-    progress.spinner("Converting to Markdown...");
-    let converter = htmd::HtmlToMarkdown::builder()
-        .skip_tags(vec!["script", "style", "noscript", "svg"])
-        .options(htmd::options::Options {
-            ul_bullet_spacing: 1,
-            ol_number_spacing: 1,
-            ..Default::default()
-        })
-        .build();
+/// 断片の Markdown 間の区切り
+const FRAGMENT_SEPARATOR: &str = "\n\n---\n\n";
+
+/// HTML 断片をそれぞれ Markdown に変換し、区切り線でつないで整形する
+fn convert_fragments(html_fragments: &[String], options: &ConvertOptions) -> Result<String> {
+    Ok(compact_markdown(
+        &convert_each(html_fragments, options)?.join(FRAGMENT_SEPARATOR),
+    ))
+}
+
+/// HTML 断片をそれぞれ Markdown に変換する
+fn convert_each(html_fragments: &[String], options: &ConvertOptions) -> Result<Vec<String>> {
+    let converter = options.converter();
     let mut md_parts = Vec::new();
-    for html in &html_fragments {
+    for html in html_fragments {
         let md = converter
             .convert(html)
             .context("Failed to convert HTML to Markdown")?;
         md_parts.push(md);
     }
+    Ok(md_parts)
+}
 
-    let markdown = compact_markdown(&md_parts.join("\n\n---\n\n"));
-    let markdown = resolve_markdown_urls(&markdown, &cli.url);
-    progress.finish("Converted to Markdown");
-
-    // 出力内容を確定する（末尾改行を保証）
-    let output_bytes = if cli.output.is_some() && !markdown.ends_with('\n') {
-        format!("{markdown}\n")
-    } else {
-        markdown
-    };
-
-    // 出力
-    let old_content = cli.output.as_ref().and_then(|p| std::fs::read(p).ok());
-    let mut writer: Box<dyn Write> = match &cli.output {
-        Some(path) => {
-            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
-                std::fs::create_dir_all(parent).with_context(|| {
-                    format!("Failed to create output directory: {}", parent.display())
-                })?;
+/// セレクタに一致した全ノードの HTML と文書内位置を取得する。
+///
+/// `frames` があればクロスオリジンの iframe でも同じスクリプトを評価する。
+/// 親文書内の位置は分からないため、フレームの一致は本文の後ろにフレーム順で並べる。
+fn evaluate_selector(
+    tab: &Tab,
+    frames: Option<&FrameTargets>,
+    selector: &Selector,
+    options: &ExtractOptions,
+    progress: &mut Progress,
+) -> Result<Vec<Match>> {
+    let js = extraction_script(selector, options);
+    let result = tab
+        .evaluate(&js, false)
+        .with_context(|| format!("Failed to evaluate selector '{}'", selector))?;
+    let mut matches = parse_matches(result.value.as_ref())
+        .with_context(|| format!("Failed to evaluate selector '{}'", selector))?;
+
+    if let Some(frames) = frames {
+        for (frame_idx, frame) in frames.sessions().iter().enumerate() {
+            match frames
+                .evaluate(&frame.session_id, &js)
+                .and_then(|value| parse_matches(Some(&value)))
+            {
+                Ok(frame_matches) => {
+                    matches.extend(frame_matches.into_iter().map(|mut m| {
+                        m.path.splice(0..0, [i64::MAX, frame_idx as i64]);
+                        m
+                    }));
+                }
+                Err(e) => progress.warn(format!(
+                    "failed to evaluate selector '{}' in frame {}: {e:#}",
+                    selector, frame.url
+                )),
             }
-            let file = File::create(path)
-                .with_context(|| format!("Failed to create output file: {}", path.display()))?;
-            Box::new(file)
-        }
-        None => Box::new(io::stdout().lock()),
-    };
-
-    writer
-        .write_all(output_bytes.as_bytes())
-        .context("Failed to write output")?;
-
-    // 出力成功後にのみ URL 付きの完了表示を行う
-    match &cli.output {
-        Some(path) => {
-            let (icon, status) = file_status(path, &old_content, output_bytes.as_bytes());
-            progress.complete(
-                icon,
-                &format!("{} → {} ({})", cli.url, path.display(), status),
-            );
         }
-        None => progress.complete("✔", &cli.url),
     }
 
-    Ok(())
+    pick_match_images(&mut matches, options);
+    Ok(matches)
 }
 
 /// ファイル出力のステータスを判定する。
@@ -241,7 +1278,7 @@ fn idle_browser_timeout(timeout_secs: u64) -> Duration {
     Duration::from_secs(timeout_secs.saturating_add(30))
 }
 
-/// CSS セレクタ文字列を JavaScript 文字列リテラルとしてエスケープする
+/// 文字列を JavaScript 文字列リテラルとしてエスケープする
 fn escape_js_string(s: &str) -> String {
     let mut out = String::with_capacity(s.len() + 2);
     out.push('"');
@@ -268,10 +1305,27 @@ fn compact_markdown(md: &str) -> String {
     let mut in_fenced_code_block = false;
     let mut fence_char = '\0';
     let mut fence_len = 0usize;
-
-    md.lines()
-        .map(|line| {
+    let mut in_math_block = false;
+    // 閉じる `$$` のない `$$` の行で残りの文書全体を数式とみなさないよう、最後の `$$` の行を求める
+    let lines: Vec<&str> = md.lines().collect();
+    let last_math_fence = lines.iter().rposition(|line| line.trim() == "$$");
+
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
             let trimmed_start = line.trim_start();
+            // `$$` で囲まれた数式の `|` はテーブルの区切りではない
+            if !in_fenced_code_block
+                && line.trim() == "$$"
+                && (in_math_block || last_math_fence > Some(i))
+            {
+                in_math_block = !in_math_block;
+                return line.to_string();
+            }
+            if in_math_block {
+                return line.to_string();
+            }
             if let Some((marker, marker_len)) = fence_marker(trimmed_start) {
                 if !in_fenced_code_block {
                     in_fenced_code_block = true;
@@ -279,7 +1333,9 @@ fn compact_markdown(md: &str) -> String {
                     fence_len = marker_len;
                     return line.to_string();
                 }
-                if marker == fence_char && marker_len >= fence_len {
+                // 閉じフェンスには情報文字列（言語名）を書けない
+                let bare = trimmed_start[marker_len..].trim().is_empty();
+                if marker == fence_char && marker_len >= fence_len && bare {
                     in_fenced_code_block = false;
                     fence_char = '\0';
                     fence_len = 0;
@@ -341,78 +1397,274 @@ fn split_unescaped_table_cells(inner: &str) -> Vec<&str> {
             continue;
         }
 
-        let escaped = backslash_run % 2 == 1;
-        if c == '|' && !escaped {
-            cells.push(&inner[start..i]);
-            start = i + 1;
+        let escaped = backslash_run % 2 == 1;
+        if c == '|' && !escaped {
+            cells.push(&inner[start..i]);
+            start = i + 1;
+        }
+
+        backslash_run = 0;
+    }
+
+    cells.push(&inner[start..]);
+    cells
+}
+
+/// Markdown に含まれる相対 URL をページ URL を基準に絶対 URL へ解決する。
+///
+/// 対象はインラインのリンク・画像、参照定義（`[id]: url`）、自動リンク、
+/// 生の HTML の URL 属性（`href` / `src` / `srcset` など）。
+/// コードスパンやコードブロック内の `[a](b)` はそのまま残す。
+fn resolve_markdown_urls(md: &str, base_url: &str) -> String {
+    rewrite_markdown_links(
+        md,
+        &LinkRewriter::new(base_url, LinkMode::Absolute, None, &[], None),
+        &HashSet::new(),
+    )
+}
+
+/// Markdown の画像のリンク先だけを `base_url` で絶対 URL にする
+/// （リンクとは別の URL を基準に画像を解決するサイト用）。対象は `rewrite_markdown_images` と同じ。
+fn resolve_markdown_images(md: &str, base_url: &str) -> String {
+    let links = LinkRewriter::new(base_url, LinkMode::Absolute, None, &[], None);
+    rewrite_markdown_images(md, &mut |url| match links.rewrite(url) {
+        Some(absolute) => ImageAction::Replace(absolute),
+        None => ImageAction::Keep,
+    })
+}
+
+/// Markdown に含まれる URL を `links` で書き換える
+/// （`--links` / `--rewrite-link` / `--clean-urls` の適用）。対象は `resolve_markdown_urls` と同じ。
+/// `keep` の URL（保存した画像など）はそのまま残す。
+fn rewrite_markdown_links(md: &str, links: &LinkRewriter, keep: &HashSet<String>) -> String {
+    rewrite_markdown_urls(md, &mut |url| {
+        if keep.contains(url) {
+            None
+        } else {
+            links.rewrite(url)
+        }
+    })
+}
+
+/// Markdown に含まれる URL を `map` で書き換える。`map` が `None` を返した URL はそのまま残す。
+///
+/// CommonMark として解釈し、実際のリンク先だけを対象にする。
+/// リンク先の山括弧・エスケープ・タイトルは元の表記のまま保つ。
+fn rewrite_markdown_urls(md: &str, map: &mut dyn FnMut(&str) -> Option<String>) -> String {
+    let parser = pulldown_cmark::Parser::new_ext(md, MARKDOWN_OPTIONS);
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+
+    // 参照定義 `[id]: url "title"`
+    for (_, definition) in parser.reference_definitions().iter() {
+        if let Some((dest, start)) = definition_destination(md, &definition.span) {
+            edits.extend(rewrite_destination(dest, start, map));
+        }
+    }
+
+    let events: Vec<_> = parser.into_offset_iter().collect();
+    for (i, (event, range)) in events.iter().enumerate() {
+        match event {
+            Event::Start(Tag::Link {
+                link_type: LinkType::Inline,
+                ..
+            }) => {}
+            Event::Start(Tag::Image {
+                link_type: LinkType::Inline,
+                ..
+            }) => {}
+            Event::Start(Tag::Link {
+                link_type: LinkType::Autolink,
+                ..
+            }) => {
+                let url = range.start + 1..range.end - 1;
+                if let Some(new) = map(&md[url.clone()]) {
+                    edits.push((url, new));
+                }
+                continue;
+            }
+            Event::Start(Tag::HtmlBlock) | Event::InlineHtml(_) => {
+                let html = &md[range.clone()];
+                let new = rewrite_html_urls(html, map);
+                if new != html {
+                    edits.push((range.clone(), new));
+                }
+                continue;
+            }
+            _ => continue,
+        }
+
+        if let Some(link) = inline_link(md, &events, i) {
+            edits.extend(rewrite_destination(
+                &md[link.destination.clone()],
+                link.destination.start,
+                map,
+            ));
         }
-
-        backslash_run = 0;
     }
-
-    cells.push(&inner[start..]);
-    cells
+    apply_edits(md, edits)
 }
 
-/// Markdown のリンク/画像構文 `[text](url)` に含まれる相対 URL を
-/// ページ URL を基準に絶対 URL へ解決する。
-fn resolve_markdown_urls(md: &str, base_url: &str) -> String {
-    let base = match Url::parse(base_url) {
-        Ok(u) => u,
-        Err(_) => return md.to_string(),
-    };
-
-    let mut result = String::with_capacity(md.len());
-    let mut cursor = 0usize;
+/// 参照定義 `[id]: url "title"`（`span`）のリンク先とタイトルの部分と、その開始位置
+fn definition_destination<'a>(md: &'a str, span: &Range<usize>) -> Option<(&'a str, usize)> {
+    let text = &md[span.clone()];
+    let colon = text.find("]:")?;
+    let rest = &text[colon + 2..];
+    let dest = rest.trim_start();
+    Some((dest, span.start + colon + 2 + (rest.len() - dest.len())))
+}
 
-    while let Some(rel) = md[cursor..].find("](") {
-        let open = cursor + rel;
-        let inside_start = open + 2;
-
-        result.push_str(&md[cursor..inside_start]);
-
-        let part = &md[inside_start..];
-        if let Some(close) = find_link_close_paren(part) {
-            let inside = &part[..close];
-            let (url, title, use_angle_brackets) = split_link_destination(inside);
-
-            if !url.is_empty() {
-                match base.join(url) {
-                    Ok(resolved) => {
-                        if use_angle_brackets {
-                            result.push('<');
-                            result.push_str(resolved.as_str());
-                            result.push('>');
-                        } else {
-                            result.push_str(resolved.as_str());
+/// Markdown の画像を `action` に従って書き換える（リンク先の置き換え、または代替テキストへの置き換え）。
+///
+/// 対象はインライン画像 `![alt](url)`、参照形式の画像の参照定義、生の HTML の `<img>`・`<source>`。
+fn rewrite_markdown_images(md: &str, action: &mut dyn FnMut(&str) -> ImageAction) -> String {
+    let parser = pulldown_cmark::Parser::new_ext(md, MARKDOWN_OPTIONS);
+    let definitions: Vec<(String, Range<usize>)> = parser
+        .reference_definitions()
+        .iter()
+        .map(|(label, definition)| (label.to_lowercase(), definition.span.clone()))
+        .collect();
+    let events: Vec<_> = parser.into_offset_iter().collect();
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    // 書き換えた参照定義（複数の画像から参照されても 1 度だけ書き換える）
+    let mut rewritten = HashSet::new();
+    for (i, (event, range)) in events.iter().enumerate() {
+        match event {
+            Event::Start(Tag::Image {
+                link_type: LinkType::Inline,
+                ..
+            }) => {
+                let Some(link) = inline_link(md, &events, i) else {
+                    continue;
+                };
+                let inside = &md[link.destination.clone()];
+                let (url, _, _) = split_link_destination(inside);
+                match action(url) {
+                    ImageAction::Keep => {}
+                    ImageAction::Replace(new) => edits.extend(rewrite_destination(
+                        inside,
+                        link.destination.start,
+                        &mut |_| Some(new.clone()),
+                    )),
+                    ImageAction::Strip => edits.push((range.clone(), md[link.label].to_string())),
+                }
+            }
+            Event::Start(Tag::Image {
+                link_type: LinkType::Reference | LinkType::Collapsed | LinkType::Shortcut,
+                dest_url,
+                id,
+                ..
+            }) => {
+                let id = id.to_lowercase();
+                let Some((_, span)) = definitions.iter().find(|(label, _)| *label == id) else {
+                    continue;
+                };
+                match action(dest_url) {
+                    ImageAction::Keep => {}
+                    ImageAction::Replace(new) => {
+                        if rewritten.insert(span.start)
+                            && let Some((dest, start)) = definition_destination(md, span)
+                        {
+                            edits.extend(rewrite_destination(dest, start, &mut |_| {
+                                Some(new.clone())
+                            }));
                         }
                     }
-                    Err(_) => {
-                        if use_angle_brackets {
-                            result.push('<');
-                            result.push_str(url);
-                            result.push('>');
-                        } else {
-                            result.push_str(url);
-                        }
+                    ImageAction::Strip => {
+                        let label = range.start + 2;
+                        let label = label..children_end(&events, i, label);
+                        edits.push((range.clone(), md[label].to_string()));
                     }
                 }
-            } else if use_angle_brackets {
-                result.push_str("<>");
             }
-            result.push_str(title);
-            result.push(')');
-            cursor = inside_start + close + 1;
-        } else {
-            result.push_str(part);
-            return result;
+            Event::Start(Tag::HtmlBlock) | Event::InlineHtml(_) => {
+                let html = &md[range.clone()];
+                let new = rewrite_html_images(html, action);
+                if new != html {
+                    edits.push((range.clone(), new));
+                }
+            }
+            _ => {}
+        }
+    }
+    apply_edits(md, edits)
+}
+
+/// インラインのリンク・画像の各部分の位置
+struct InlineLink {
+    /// リンクテキスト（`[` と `]` の内側）
+    label: Range<usize>,
+    /// `(` と `)` の内側（リンク先とタイトル）
+    destination: Range<usize>,
+}
+
+/// `events[i]` から始まるインラインのリンク・画像の各部分を探す
+fn inline_link(md: &str, events: &[(Event, Range<usize>)], i: usize) -> Option<InlineLink> {
+    let (event, range) = &events[i];
+    let is_image = matches!(event, Event::Start(Tag::Image { .. }));
+    let label_start = range.start + if is_image { 2 } else { 1 };
+
+    // リンクテキストの終わり（子要素の後ろの `](`）を探す
+    let label_end = children_end(events, i, label_start);
+    let label_end = label_end + md[label_end..range.end].find("](")?;
+    let start = label_end + 2;
+    let close = find_link_close_paren(&md[start..range.end])?;
+    Some(InlineLink {
+        label: label_start..label_end,
+        destination: start..start + close,
+    })
+}
+
+/// `events[i]` の子要素の終わりの位置（子要素がなければ `start`）
+fn children_end(events: &[(Event, Range<usize>)], i: usize, start: usize) -> usize {
+    let mut end = start;
+    let mut depth = 0usize;
+    for (event, child) in &events[i + 1..] {
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) if depth == 0 => break,
+            Event::End(_) => depth -= 1,
+            _ => {}
         }
+        end = end.max(child.end);
     }
+    end
+}
 
+/// 重ならない書き換えを位置の順に適用する。
+/// 開始位置が同じ書き換え（挿入とその直後の削除など）は追加した順に適用する。
+fn apply_edits(md: &str, mut edits: Vec<(Range<usize>, String)>) -> String {
+    // 画像を含むリンクでは内側の画像のリンク先が先に現れる
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut result = String::with_capacity(md.len());
+    let mut cursor = 0usize;
+    for (range, new) in edits {
+        result.push_str(&md[cursor..range.start]);
+        result.push_str(&new);
+        cursor = range.end;
+    }
     result.push_str(&md[cursor..]);
     result
 }
 
+/// `start` から始まるリンク先（とタイトル）のうち、リンク先の部分の書き換えを返す。
+fn rewrite_destination(
+    inside: &str,
+    start: usize,
+    map: &mut dyn FnMut(&str) -> Option<String>,
+) -> Option<(Range<usize>, String)> {
+    let (url, title, use_angle_brackets) = split_link_destination(inside);
+    if url.is_empty() {
+        return None;
+    }
+    let new = map(url)?;
+    let new = if use_angle_brackets {
+        format!("<{new}>")
+    } else {
+        new
+    };
+    Some((start..start + inside.len() - title.len(), new))
+}
+
 /// Markdown のリンク先を URL とタイトルに分割する。
 ///
 /// 対応形式:
@@ -552,65 +1804,565 @@ mod tests {
     }
 
     #[test]
-    fn escape_complex_css_selector() {
-        assert_eq!(
-            escape_js_string("div > .content p:nth-child(2)"),
-            r#""div > .content p:nth-child(2)""#,
-        );
+    fn escape_complex_css_selector() {
+        assert_eq!(
+            escape_js_string("div > .content p:nth-child(2)"),
+            r#""div > .content p:nth-child(2)""#,
+        );
+    }
+
+    #[test]
+    fn cli_default_values() {
+        let cli = Cli::try_parse_from(["get-md", "https://example.com"]).unwrap();
+        assert_eq!(cli.urls, vec!["https://example.com"]);
+        assert!(cli.selector.is_empty());
+        assert!(cli.output.is_none());
+        assert!(cli.chrome_path.is_none());
+        assert_eq!(cli.wait, 2);
+        assert_eq!(cli.timeout, 60);
+        assert!(!cli.no_headless);
+        assert!(!cli.pierce);
+        assert!(cli.preset.is_none());
+        assert!(cli.front_matter.is_none());
+        assert_eq!(cli.format, OutputFormat::Markdown);
+        assert!(!cli.include_html);
+        assert!(!cli.no_adapters);
+        assert!(!cli.prefer_source);
+        assert_eq!(cli.order, Order::Selector);
+        assert!(!cli.strict);
+        assert!(cli.expect.is_empty());
+        assert!(!cli.first);
+        assert!(cli.nth.is_none());
+        assert!(!cli.quiet);
+    }
+
+    #[test]
+    fn cli_all_options() {
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://example.com",
+            "-s",
+            "article",
+            "-s",
+            ".content",
+            "-o",
+            "out.md",
+            "-w",
+            "5",
+            "-t",
+            "60",
+            "--no-headless",
+            "--no-cache",
+            "-q",
+        ])
+        .unwrap();
+        assert_eq!(cli.urls, vec!["https://example.com"]);
+        assert_eq!(cli.selector, vec!["article", ".content"]);
+        assert_eq!(cli.output.unwrap().to_str().unwrap(), "out.md");
+        assert_eq!(cli.wait, 5);
+        assert_eq!(cli.timeout, 60);
+        assert!(cli.no_headless);
+        assert!(cli.no_cache);
+        assert!(cli.quiet);
+    }
+
+    #[test]
+    fn cli_missing_url_fails() {
+        assert!(Cli::try_parse_from(["get-md"]).is_err());
+    }
+
+    #[test]
+    fn cli_multiple_urls() {
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://example.com/a",
+            "https://example.com/b",
+            "-s",
+            "main",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli.urls,
+            vec!["https://example.com/a", "https://example.com/b"]
+        );
+        assert_eq!(cli.selector, vec!["main"]);
+    }
+
+    #[test]
+    fn cli_conversion_style_options() {
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://example.com",
+            "--heading",
+            "setext",
+            "--bullet",
+            "dash",
+            "--code-block",
+            "indented",
+            "--emphasis",
+            "underscore",
+            "--line-break",
+            "backslash",
+            "--skip-tag",
+            "nav",
+            "--keep-tag",
+            "svg",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli.convert_options(),
+            ConvertOptions {
+                heading: Heading::Setext,
+                bullet: Bullet::Dash,
+                code_block: CodeBlock::Indented,
+                emphasis: Emphasis::Underscore,
+                line_break: LineBreak::Backslash,
+                skip_tags: vec!["nav".to_string()],
+                keep_tags: vec!["svg".to_string()],
+            }
+        );
+        let cli = Cli::try_parse_from(["get-md", "https://example.com"]).unwrap();
+        assert_eq!(cli.convert_options(), ConvertOptions::default());
+    }
+
+    #[test]
+    fn cli_link_style_option() {
+        let cli = Cli::try_parse_from(["get-md", "https://example.com"]).unwrap();
+        assert_eq!(cli.link_style, LinkStyle::Inline);
+        assert!(!cli.reference_images);
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://example.com",
+            "--link-style",
+            "footnote",
+            "--reference-images",
+        ])
+        .unwrap();
+        assert_eq!(cli.link_style, LinkStyle::Footnote);
+        assert!(cli.reference_images);
+    }
+
+    #[test]
+    fn finish_markdown_resolves_then_restyles() {
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://example.com/docs/",
+            "--link-style",
+            "reference",
+        ])
+        .unwrap();
+        assert_eq!(
+            finish_markdown(
+                "[a](intro) ![i](i.png)",
+                "https://example.com/docs/",
+                &cli,
+                None
+            ),
+            "[a][1] ![i](https://example.com/docs/i.png)\n\n[1]: https://example.com/docs/intro"
+        );
+    }
+
+    #[test]
+    fn cli_link_rewriting_options() {
+        let cli = Cli::try_parse_from(["get-md", "https://example.com"]).unwrap();
+        assert_eq!(cli.links, LinkMode::Absolute);
+        assert!(cli.link_base.is_none());
+        assert!(cli.rewrite_link.is_empty());
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://example.com",
+            "--links",
+            "relative-to",
+            "--link-base",
+            "https://example.com/docs/",
+            "--rewrite-link",
+            "^https://cdn\\.test/=>/assets/",
+            "--rewrite-link",
+            "a=>b",
+        ])
+        .unwrap();
+        assert_eq!(cli.links, LinkMode::RelativeTo);
+        assert_eq!(
+            cli.link_base.as_ref().map(Url::as_str),
+            Some("https://example.com/docs/")
+        );
+        assert_eq!(cli.rewrite_link.len(), 2);
+        assert!(
+            Cli::try_parse_from(["get-md", "https://example.com", "--rewrite-link", "a"]).is_err()
+        );
+    }
+
+    #[test]
+    fn finish_markdown_cleans_urls() {
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://example.com/",
+            "--clean-urls",
+            "--tracking-param",
+            "ref",
+        ])
+        .unwrap();
+        assert_eq!(
+            finish_markdown(
+                "[a](/p?id=1&utm_source=x&ref=hn) ![i](https://cdn.test:443/%7ei.png?fbclid=1)\n\n[b]: /q?gclid=2",
+                "https://example.com/",
+                &cli,
+                None
+            ),
+            "[a](https://example.com/p?id=1) ![i](https://cdn.test/~i.png)\n\n[b]: https://example.com/q"
+        );
+        // 指定しなければトラッキング用のパラメータも残す
+        let cli = Cli::try_parse_from(["get-md", "https://example.com/"]).unwrap();
+        assert_eq!(
+            finish_markdown("[a](/p?utm_source=x)", "https://example.com/", &cli, None),
+            "[a](https://example.com/p?utm_source=x)"
+        );
+    }
+
+    #[test]
+    fn finish_markdown_applies_link_rewriting() {
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://docs.vendor.com/guide/intro",
+            "--links",
+            "keep",
+            "--rewrite-link",
+            "^https://docs\\.vendor\\.com/(.*)=>./vendor/$1.md",
+        ])
+        .unwrap();
+        assert_eq!(
+            finish_markdown(
+                "[a](../api) [b](https://other.test/x)\n\n<img src=\"/logo.png\">",
+                "https://docs.vendor.com/guide/intro",
+                &cli,
+                None
+            ),
+            "[a](./vendor/api.md) [b](https://other.test/x)\n\n<img src=\"./vendor/logo.png.md\">"
+        );
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://docs.vendor.com/guide/intro",
+            "--links",
+            "relative-to",
+            "--link-base",
+            "https://docs.vendor.com/",
+        ])
+        .unwrap();
+        assert_eq!(
+            finish_markdown(
+                "[a](../api) [b](https://other.test/x)",
+                "https://docs.vendor.com/guide/intro",
+                &cli,
+                None
+            ),
+            "[a](api) [b](https://other.test/x)"
+        );
+    }
+
+    #[test]
+    fn cli_format_option() {
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://example.com",
+            "--format",
+            "json",
+            "--include-html",
+        ])
+        .unwrap();
+        assert_eq!(cli.format, OutputFormat::Json);
+        assert!(cli.include_html);
+        let cli =
+            Cli::try_parse_from(["get-md", "https://example.com", "--format", "ndjson"]).unwrap();
+        assert_eq!(cli.format, OutputFormat::Ndjson);
+        let cli =
+            Cli::try_parse_from(["get-md", "https://example.com", "--format", "text"]).unwrap();
+        assert_eq!(cli.format, OutputFormat::Text);
+        let cli =
+            Cli::try_parse_from(["get-md", "https://example.com", "--format", "html"]).unwrap();
+        assert_eq!(cli.format, OutputFormat::Html);
+        assert!(
+            Cli::try_parse_from(["get-md", "https://example.com", "--format", "yaml"]).is_err()
+        );
+    }
+
+    #[test]
+    fn selector_specs_default_to_body() {
+        let cli = Cli::try_parse_from(["get-md", "https://example.com"]).unwrap();
+        let specs: Vec<String> = selector_specs(&cli)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(specs, vec!["body"]);
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://example.com",
+            "-s",
+            "main",
+            "--xpath",
+            "//article",
+        ])
+        .unwrap();
+        let specs: Vec<String> = selector_specs(&cli)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(specs, vec!["main", "//article"]);
+    }
+
+    #[test]
+    fn cli_single_selector() {
+        let cli = Cli::try_parse_from(["get-md", "https://example.com", "-s", "main"]).unwrap();
+        assert_eq!(cli.selector, vec!["main"]);
+    }
+
+    #[test]
+    fn cli_xpath_option() {
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://example.com",
+            "--xpath",
+            "//h2[text()='Usage']",
+            "-s",
+            "xpath://main",
+        ])
+        .unwrap();
+        assert_eq!(cli.xpath, vec!["//h2[text()='Usage']"]);
+        assert_eq!(cli.selector, vec!["xpath://main"]);
+    }
+
+    #[test]
+    fn cli_strict_and_expect_options() {
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://example.com",
+            "-s",
+            "article h2",
+            "--strict",
+            "--expect",
+            "article h2>=3",
+        ])
+        .unwrap();
+        assert!(cli.strict);
+        assert_eq!(
+            cli.expect,
+            vec![Expectation {
+                selector: "article h2".to_string(),
+                min: 3,
+            }],
+        );
+    }
+
+    #[test]
+    fn cli_expect_rejects_invalid_value() {
+        assert!(
+            Cli::try_parse_from(["get-md", "https://example.com", "--expect", "main"]).is_err()
+        );
+    }
+
+    #[test]
+    fn cli_first_option() {
+        let cli = Cli::try_parse_from(["get-md", "https://example.com", "--first"]).unwrap();
+        assert!(cli.first);
+    }
+
+    #[test]
+    fn cli_nth_option() {
+        let cli = Cli::try_parse_from(["get-md", "https://example.com", "--nth", "3"]).unwrap();
+        assert_eq!(cli.nth, Some(3));
+    }
+
+    #[test]
+    fn cli_nth_rejects_zero() {
+        assert!(Cli::try_parse_from(["get-md", "https://example.com", "--nth", "0"]).is_err());
+    }
+
+    #[test]
+    fn cli_first_conflicts_with_nth() {
+        assert!(
+            Cli::try_parse_from(["get-md", "https://example.com", "--first", "--nth", "2"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn cli_order_option() {
+        let cli =
+            Cli::try_parse_from(["get-md", "https://example.com", "--order", "document"]).unwrap();
+        assert_eq!(cli.order, Order::Document);
+        assert!(
+            Cli::try_parse_from(["get-md", "https://example.com", "--order", "random"]).is_err()
+        );
+    }
+
+    #[test]
+    fn cli_pierce_option() {
+        let cli = Cli::try_parse_from(["get-md", "https://example.com", "--pierce"]).unwrap();
+        assert!(cli.pierce);
+    }
+
+    #[test]
+    fn cli_preset_option() {
+        let cli =
+            Cli::try_parse_from(["get-md", "https://example.com", "--preset", "auto"]).unwrap();
+        assert_eq!(cli.preset, Some(Preset::Auto));
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://example.com",
+            "--preset",
+            "mkdocs-material",
+        ])
+        .unwrap();
+        assert_eq!(cli.preset, Some(Preset::MkdocsMaterial));
+        assert!(
+            Cli::try_parse_from(["get-md", "https://example.com", "--preset", "hugo"]).is_err()
+        );
+    }
+
+    #[test]
+    fn cli_no_adapters_option() {
+        let cli =
+            Cli::try_parse_from(["get-md", "https://github.com/o/r", "--no-adapters"]).unwrap();
+        assert!(cli.no_adapters);
     }
 
     #[test]
-    fn cli_default_values() {
-        let cli = Cli::try_parse_from(["get-md", "https://example.com"]).unwrap();
-        assert_eq!(cli.url, "https://example.com");
-        assert!(cli.selector.is_empty());
-        assert!(cli.output.is_none());
-        assert!(cli.chrome_path.is_none());
-        assert_eq!(cli.wait, 2);
-        assert_eq!(cli.timeout, 60);
-        assert!(!cli.no_headless);
-        assert!(!cli.quiet);
+    fn cli_front_matter_option() {
+        let cli = Cli::try_parse_from(["get-md", "https://example.com", "--front-matter", "yaml"])
+            .unwrap();
+        assert_eq!(cli.front_matter, Some(FrontMatter::Yaml));
+        let cli = Cli::try_parse_from(["get-md", "https://example.com", "--front-matter", "toml"])
+            .unwrap();
+        assert_eq!(cli.front_matter, Some(FrontMatter::Toml));
+        assert!(
+            Cli::try_parse_from(["get-md", "https://example.com", "--front-matter", "json"])
+                .is_err()
+        );
     }
 
     #[test]
-    fn cli_all_options() {
+    fn cli_exclude_and_header_options() {
         let cli = Cli::try_parse_from([
             "get-md",
             "https://example.com",
-            "-s",
-            "article",
-            "-s",
-            ".content",
-            "-o",
-            "out.md",
-            "-w",
-            "5",
-            "-t",
-            "60",
-            "--no-headless",
-            "--no-cache",
-            "-q",
+            "--exclude",
+            ".ad",
+            "--exclude",
+            "nav",
+            "-H",
+            "Authorization: Bearer x",
+            "--print-config",
         ])
         .unwrap();
-        assert_eq!(cli.url, "https://example.com");
-        assert_eq!(cli.selector, vec!["article", ".content"]);
-        assert_eq!(cli.output.unwrap().to_str().unwrap(), "out.md");
+        assert_eq!(cli.exclude, vec![".ad", "nav"]);
+        assert_eq!(cli.header[0].name, "Authorization");
+        assert_eq!(cli.header[0].value, "Bearer x");
+        assert!(cli.print_config);
+    }
+
+    #[test]
+    fn cli_header_rejects_invalid_value() {
+        assert!(Cli::try_parse_from(["get-md", "https://example.com", "-H", "novalue"]).is_err());
+    }
+
+    // Cli::apply_settings のテスト
+
+    fn parse_with_settings(args: &[&str], settings: &Settings) -> Cli {
+        let matches = Cli::command().try_get_matches_from(args).unwrap();
+        let mut cli = Cli::from_arg_matches(&matches).unwrap();
+        cli.apply_settings(&matches, settings);
+        cli
+    }
+
+    #[test]
+    fn apply_settings_fills_unspecified_options() {
+        let settings = Settings {
+            selector: Some(vec!["article".to_string()]),
+            exclude: Some(vec![".ad".to_string()]),
+            wait: Some(5),
+            output: Some(PathBuf::from("out.md")),
+            front_matter: Some(FrontMatter::Toml),
+            order: Some(Order::Document),
+            no_cache: Some(true),
+            links: Some(LinkMode::RelativeTo),
+            link_base: Url::parse("https://example.com/docs/").ok(),
+            ..Settings::default()
+        };
+        let cli = parse_with_settings(&["get-md", "https://example.com"], &settings);
+        assert_eq!(cli.selector, vec!["article"]);
+        assert_eq!(cli.exclude, vec![".ad"]);
         assert_eq!(cli.wait, 5);
         assert_eq!(cli.timeout, 60);
-        assert!(cli.no_headless);
+        assert_eq!(cli.output, Some(PathBuf::from("out.md")));
+        assert_eq!(cli.front_matter, Some(FrontMatter::Toml));
+        assert_eq!(cli.order, Order::Document);
         assert!(cli.no_cache);
-        assert!(cli.quiet);
+        assert_eq!(cli.links, LinkMode::RelativeTo);
+        assert_eq!(
+            cli.link_base.as_ref().map(Url::as_str),
+            Some("https://example.com/docs/")
+        );
     }
 
     #[test]
-    fn cli_missing_url_fails() {
-        assert!(Cli::try_parse_from(["get-md"]).is_err());
+    fn apply_settings_keeps_explicit_cli_values() {
+        let settings = Settings {
+            selector: Some(vec!["article".to_string()]),
+            wait: Some(5),
+            pierce: Some(true),
+            ..Settings::default()
+        };
+        let cli = parse_with_settings(
+            &["get-md", "https://example.com", "-s", "main", "-w", "2"],
+            &settings,
+        );
+        assert_eq!(cli.selector, vec!["main"]);
+        assert_eq!(cli.wait, 2);
+        assert!(cli.pierce);
     }
 
     #[test]
-    fn cli_single_selector() {
+    fn apply_settings_xpath_on_cli_replaces_profile_selectors() {
+        let settings = Settings {
+            selector: Some(vec!["article".to_string()]),
+            ..Settings::default()
+        };
+        let cli = parse_with_settings(
+            &["get-md", "https://example.com", "--xpath", "//main"],
+            &settings,
+        );
+        assert!(cli.selector.is_empty());
+        assert_eq!(cli.xpath, vec!["//main"]);
+    }
+
+    #[test]
+    fn apply_settings_merges_headers() {
+        let settings = Settings {
+            headers: Some(
+                [("authorization", "old"), ("X-Team", "docs")]
+                    .into_iter()
+                    .map(|(n, v)| (n.to_string(), v.to_string()))
+                    .collect(),
+            ),
+            ..Settings::default()
+        };
+        let cli = parse_with_settings(
+            &["get-md", "https://example.com", "-H", "Authorization: new"],
+            &settings,
+        );
+        let headers: Vec<String> = cli.header.iter().map(ToString::to_string).collect();
+        assert_eq!(headers, vec!["X-Team: docs", "Authorization: new"]);
+    }
+
+    #[test]
+    fn effective_settings_serializes_to_toml() {
         let cli = Cli::try_parse_from(["get-md", "https://example.com", "-s", "main"]).unwrap();
-        assert_eq!(cli.selector, vec!["main"]);
+        let text = toml::to_string_pretty(&cli.effective_settings()).unwrap();
+        assert!(text.contains("selector = [\"main\"]"));
+        assert!(text.contains("wait = 2"));
+        assert!(text.contains("order = \"selector\""));
+        assert!(text.contains("no-cache = false"));
+        assert!(!text.contains("output"));
+        assert!(!text.contains("match"));
     }
 
     #[test]
@@ -656,6 +2408,21 @@ mod tests {
         assert_eq!(escape_js_string("div[data-x='y']"), r#""div[data-x='y']""#);
     }
 
+    // convert_fragments のテスト
+
+    #[test]
+    fn convert_fragments_joins_with_separator() {
+        let md = convert_fragments(
+            &[
+                "<h1>Title</h1>".to_string(),
+                "<p>Body <script>x()</script></p>".to_string(),
+            ],
+            &ConvertOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(md, "# Title\n\n---\n\nBody");
+    }
+
     // compact_markdown のテスト
 
     #[test]
@@ -927,6 +2694,15 @@ mod tests {
 
     // resolve_markdown_urls の追加境界ケース
 
+    #[test]
+    fn resolve_link_between_dollar_signs() {
+        // `$1 ... $2` を数式として解釈するとリンクが書き換えられない
+        assert_eq!(
+            resolve_markdown_urls("Set FOO=$1 and see [x](./y) or BAR=$2", BASE),
+            "Set FOO=$1 and see [x](https://example.com/docs/en/y) or BAR=$2",
+        );
+    }
+
     #[test]
     fn resolve_url_with_query_string() {
         assert_eq!(
@@ -1117,6 +2893,53 @@ no closing fence";
         assert_eq!(compact_markdown(input), input);
     }
 
+    #[test]
+    fn compact_fence_with_language() {
+        let input = "\
+```rust
+| padded           | table           |
+```md
+| still            | code            |
+```
+| a   | b   |";
+        assert_eq!(
+            compact_markdown(input),
+            "\
+```rust
+| padded           | table           |
+```md
+| still            | code            |
+```
+| a | b |"
+        );
+    }
+
+    #[test]
+    fn compact_skips_display_math() {
+        let input = "\
+$$
+| x |   +   | y |
+$$
+| a   | b   |";
+        assert_eq!(
+            compact_markdown(input),
+            "\
+$$
+| x |   +   | y |
+$$
+| a | b |"
+        );
+    }
+
+    #[test]
+    fn compact_ignores_unclosed_display_math() {
+        let input = "\
+| x |
+$$
+| a   | b   |";
+        assert_eq!(compact_markdown(input), "| x |\n$$\n| a | b |");
+    }
+
     #[test]
     fn compact_fence_longer_close() {
         let input = "\
@@ -1228,4 +3051,375 @@ more code
             "[doc](<https://example.com/docs/en/file).md>)",
         );
     }
+
+    // コード内の `[a](b)` は書き換えない
+
+    #[test]
+    fn resolve_skips_code_span() {
+        assert_eq!(
+            resolve_markdown_urls("`[a](./x)` and [b](./y)", BASE),
+            "`[a](./x)` and [b](https://example.com/docs/en/y)",
+        );
+    }
+
+    #[test]
+    fn resolve_skips_code_blocks() {
+        let input = "```\n[a](./x)\n![b](./y.png)\n```\n\n    [c](./z)";
+        assert_eq!(resolve_markdown_urls(input, BASE), input);
+    }
+
+    #[test]
+    fn resolve_skips_text_that_is_not_a_link() {
+        let input = r"\[a](./x) and a ](./y) marker";
+        assert_eq!(resolve_markdown_urls(input, BASE), input);
+    }
+
+    #[test]
+    fn resolve_code_span_inside_link_text() {
+        assert_eq!(
+            resolve_markdown_urls("[`f](x)`](./y)", BASE),
+            "[`f](x)`](https://example.com/docs/en/y)",
+        );
+    }
+
+    #[test]
+    fn resolve_linked_image() {
+        assert_eq!(
+            resolve_markdown_urls("[![badge](./b.svg)](./ci)", BASE),
+            "[![badge](https://example.com/docs/en/b.svg)](https://example.com/docs/en/ci)",
+        );
+    }
+
+    #[test]
+    fn resolve_link_in_table_cell() {
+        assert_eq!(
+            resolve_markdown_urls("| a |\n| - |\n| [x](./x) |", BASE),
+            "| a |\n| - |\n| [x](https://example.com/docs/en/x) |",
+        );
+    }
+
+    // 参照定義・自動リンク・生の HTML の URL
+
+    #[test]
+    fn resolve_reference_definitions() {
+        assert_eq!(
+            resolve_markdown_urls(
+                "[a][id] and [b]\n\n[id]: ./x \"Title\"\n[b]:\n  <./my file.md>",
+                BASE
+            ),
+            "[a][id] and [b]\n\n[id]: https://example.com/docs/en/x \"Title\"\n[b]:\n  <https://example.com/docs/en/my%20file.md>",
+        );
+    }
+
+    #[test]
+    fn resolve_skips_definition_in_code_block() {
+        let input = "```\n[id]: ./x\n```";
+        assert_eq!(resolve_markdown_urls(input, BASE), input);
+    }
+
+    #[test]
+    fn resolve_autolink_is_normalized() {
+        assert_eq!(
+            resolve_markdown_urls("<https://other.com> and <me@example.com>", BASE),
+            "<https://other.com/> and <me@example.com>",
+        );
+    }
+
+    #[test]
+    fn resolve_inline_html_attributes() {
+        assert_eq!(
+            resolve_markdown_urls(
+                r#"x<sub><a href="./n">1</a></sub> <img src="/i.png" srcset="i2.png 2x">"#,
+                BASE
+            ),
+            r#"x<sub><a href="https://example.com/docs/en/n">1</a></sub> <img src="https://example.com/i.png" srcset="https://example.com/docs/en/i2.png 2x">"#,
+        );
+    }
+
+    #[test]
+    fn resolve_html_block_attributes() {
+        assert_eq!(
+            resolve_markdown_urls(
+                "<figure>\n<img\n  src=\"./f.png\">\n</figure>\n\n[a](./a)",
+                BASE
+            ),
+            "<figure>\n<img\n  src=\"https://example.com/docs/en/f.png\">\n</figure>\n\n[a](https://example.com/docs/en/a)",
+        );
+    }
+
+    #[test]
+    fn resolve_skips_html_in_code() {
+        let input = "`<a href=\"./x\">` and\n\n    <img src=\"./y\">";
+        assert_eq!(resolve_markdown_urls(input, BASE), input);
+    }
+
+    // resolve_markdown_images のテスト
+
+    #[test]
+    fn resolve_readme_images_against_raw_base() {
+        let md = "![logo](docs/logo.png) [guide](docs/guide.md)\n\n<img src=\"a.svg\">";
+        let md = resolve_markdown_images(md, "https://github.com/o/r/raw/main/README.md");
+        assert_eq!(
+            resolve_markdown_urls(&md, "https://github.com/o/r/blob/main/README.md"),
+            "![logo](https://github.com/o/r/raw/main/docs/logo.png) [guide](https://github.com/o/r/blob/main/docs/guide.md)\n\n<img src=\"https://github.com/o/r/raw/main/a.svg\">"
+        );
+    }
+
+    // request_headers のテスト
+
+    fn header(name: &str, value: &str) -> Header {
+        Header {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn request_headers_only_for_same_origin() {
+        let origin = Url::parse("https://docs.example.com/guide/")
+            .unwrap()
+            .origin();
+        let existing = Network::Headers(None);
+        let headers = [header("Authorization", "Bearer t")];
+        for url in [
+            "https://cdn.example.com/app.js",
+            "http://docs.example.com/",
+            "https://docs.example.com:8443/",
+            "https://docs.example.com.evil.test/",
+            "data:image/png;base64,AA==",
+        ] {
+            assert_eq!(
+                request_headers(url, &existing, &origin, &headers),
+                None,
+                "{url}"
+            );
+        }
+        let added = request_headers(
+            "https://docs.example.com/img/a.png",
+            &existing,
+            &origin,
+            &headers,
+        )
+        .unwrap();
+        assert_eq!(
+            added
+                .iter()
+                .map(|h| (h.name.as_str(), h.value.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("Authorization", "Bearer t")]
+        );
+    }
+
+    #[test]
+    fn request_headers_override_browser_headers() {
+        let origin = Url::parse("https://a.test/").unwrap().origin();
+        let existing = Network::Headers(Some(serde_json::json!({
+            "Accept": "text/html",
+            "user-agent": "Chrome",
+        })));
+        let headers = [header("User-Agent", "get-md"), header("Cookie", "s=1")];
+        let merged = request_headers("https://a.test/x", &existing, &origin, &headers).unwrap();
+        assert_eq!(
+            merged
+                .iter()
+                .map(|h| (h.name.as_str(), h.value.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Accept", "text/html"),
+                ("User-Agent", "get-md"),
+                ("Cookie", "s=1"),
+            ]
+        );
+    }
+
+    // apply_edits のテスト
+
+    #[test]
+    fn apply_edits_keeps_order_of_edits_at_same_start() {
+        let md = "<img srcset=\"a.png\">";
+        // 挿入（4..4）と、同じ位置から始まる削除（4..19）
+        let edits = vec![(4..4, " src=\"a.png\"".to_string()), (4..19, String::new())];
+        assert_eq!(apply_edits(md, edits), "<img src=\"a.png\">");
+    }
+
+    // text_body のテスト
+
+    #[test]
+    fn text_body_converts_markdown_documents() {
+        let md = "# Title\n\nSome **bold** [link](https://a.test/).";
+        let text = "Title\n\nSome bold link.";
+        assert_eq!(text_body(md, None), text);
+        assert_eq!(text_body(md, Some(PlainKind::Markdown)), text);
+        assert_eq!(text_body(md, Some(PlainKind::Text)), md);
+        assert_eq!(
+            text_body("{\n  \"a\": 1\n}", Some(PlainKind::Json)),
+            "{\n  \"a\": 1\n}"
+        );
+    }
+
+    // page_label のテスト
+
+    #[test]
+    fn page_label_shows_redirect_target() {
+        let mut report = PageReport {
+            url: "http://example.com/docs".to_string(),
+            final_url: Some("https://example.com/docs/".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            page_label(&report),
+            "http://example.com/docs → https://example.com/docs/"
+        );
+        // 末尾の `/` の補完だけなら URL のみ
+        report.url = "https://example.com".to_string();
+        report.final_url = Some("https://example.com/".to_string());
+        assert_eq!(page_label(&report), "https://example.com");
+        report.final_url = None;
+        assert_eq!(page_label(&report), "https://example.com");
+    }
+
+    // rewrite_markdown_images のテスト
+
+    #[test]
+    fn rewrite_images_replaces_destinations() {
+        let md = "![a](https://a.test/x.png \"T\") [![b](<https://a.test/y z.png>)](https://a.test/)\n\n`![c](https://a.test/c.png)`";
+        let mut seen = Vec::new();
+        let out = rewrite_markdown_images(md, &mut |url| {
+            seen.push(url.to_string());
+            ImageAction::Replace(format!("img/{}.png", seen.len()))
+        });
+        assert_eq!(
+            out,
+            "![a](img/1.png \"T\") [![b](<img/2.png>)](https://a.test/)\n\n`![c](https://a.test/c.png)`"
+        );
+        assert_eq!(seen, vec!["https://a.test/x.png", "https://a.test/y z.png"]);
+    }
+
+    #[test]
+    fn rewrite_images_strip_keeps_alt_text() {
+        assert_eq!(
+            rewrite_markdown_images(
+                "Logo: ![*Acme*](a.png) [![badge](b.svg)](https://ci.test/) ![](c.png)!",
+                &mut |_| ImageAction::Strip
+            ),
+            "Logo: *Acme* [badge](https://ci.test/) !"
+        );
+    }
+
+    #[test]
+    fn rewrite_images_in_reference_definitions() {
+        let md = "![a][logo] ![logo] [site][home]\n\n[logo]: ./logo.png \"Logo\"\n[home]: ./";
+        let mut seen = Vec::new();
+        let out = rewrite_markdown_images(md, &mut |url| {
+            seen.push(url.to_string());
+            ImageAction::Replace("img/logo.png".to_string())
+        });
+        assert_eq!(
+            out,
+            "![a][logo] ![logo] [site][home]\n\n[logo]: img/logo.png \"Logo\"\n[home]: ./"
+        );
+        assert_eq!(seen, vec!["./logo.png", "./logo.png"]);
+        assert_eq!(
+            rewrite_markdown_images(md, &mut |_| ImageAction::Strip),
+            "a logo [site][home]\n\n[logo]: ./logo.png \"Logo\"\n[home]: ./"
+        );
+    }
+
+    #[test]
+    fn rewrite_images_in_raw_html() {
+        let md = "<p align=\"center\"><img src=\"./a.png\" srcset=\"./a.png 1x, ./a@2x.png 2x\" alt=\"A\"></p>\n\nText <img src='b.png'> <a href=\"./c\">c</a>";
+        let out = rewrite_markdown_images(md, &mut |url| {
+            ImageAction::Replace(format!("img/{}", url.trim_start_matches("./")))
+        });
+        assert_eq!(
+            out,
+            "<p align=\"center\"><img src=\"img/a.png\" srcset=\"img/a.png 1x, img/a@2x.png 2x\" alt=\"A\"></p>\n\nText <img src='img/b.png'> <a href=\"./c\">c</a>"
+        );
+        assert_eq!(
+            rewrite_markdown_images(md, &mut |_| ImageAction::Strip),
+            "<p align=\"center\">A</p>\n\nText  <a href=\"./c\">c</a>"
+        );
+    }
+
+    #[test]
+    fn saved_images_are_not_rewritten_as_links() {
+        let link_base = Url::parse("https://a.test/").unwrap();
+        let links = LinkRewriter::new(
+            "https://a.test/docs/page",
+            LinkMode::RelativeTo,
+            Some(&link_base),
+            &[],
+            None,
+        );
+        let saved = HashSet::from(["images/x.png".to_string()]);
+        assert_eq!(
+            rewrite_markdown_links("![x](images/x.png) [y](./y)", &links, &saved),
+            "![x](images/x.png) [y](docs/y)"
+        );
+    }
+
+    #[test]
+    fn rewrite_images_keep_is_unchanged() {
+        let md = "![a](a.png) [l](x.png)";
+        assert_eq!(rewrite_markdown_images(md, &mut |_| ImageAction::Keep), md);
+    }
+
+    #[test]
+    fn cli_image_options() {
+        let cli = Cli::try_parse_from(["get-md", "https://example.com"]).unwrap();
+        assert_eq!(cli.images, ImageMode::Keep);
+        assert!(cli.download_images.is_none());
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://example.com",
+            "--images",
+            "inline-data-uri",
+        ])
+        .unwrap();
+        assert_eq!(cli.images, ImageMode::InlineDataUri);
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://example.com",
+            "--download-images",
+            "docs/img",
+        ])
+        .unwrap();
+        assert_eq!(cli.download_images, Some(PathBuf::from("docs/img")));
+    }
+
+    #[test]
+    fn cli_lazy_image_options() {
+        let cli = Cli::try_parse_from(["get-md", "https://example.com"]).unwrap();
+        assert!(!cli.no_lazy_images);
+        assert!(cli.image_width.is_none());
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://example.com",
+            "--image-width",
+            "800",
+            "--no-lazy-images",
+        ])
+        .unwrap();
+        assert_eq!(cli.image_width, Some(800));
+        assert!(cli.no_lazy_images);
+        assert!(
+            Cli::try_parse_from(["get-md", "https://example.com", "--image-width", "-1"]).is_err()
+        );
+    }
+
+    #[test]
+    fn cli_code_cleanup_options() {
+        let cli = Cli::try_parse_from(["get-md", "https://example.com"]).unwrap();
+        assert!(!cli.no_clean_code);
+        assert!(!cli.keep_prompts);
+        let cli = Cli::try_parse_from([
+            "get-md",
+            "https://example.com",
+            "--no-clean-code",
+            "--keep-prompts",
+        ])
+        .unwrap();
+        assert!(cli.no_clean_code);
+        assert!(cli.keep_prompts);
+    }
 }
@@ -0,0 +1,444 @@
+use std::rc::Rc;
+
+use markup5ever_rcdom::{Node, NodeData};
+
+use crate::convert::{parent_node, tag_name};
+
+/// 複数文字の `<mi>` のうち TeX の関数名として書くもの
+const FUNCTIONS: &[&str] = &[
+    "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "log", "ln", "lg", "exp", "lim", "max", "min", "sup", "inf", "det", "dim", "ker", "deg", "gcd",
+    "arg", "Pr",
+];
+
+/// 上下の添字を真上・真下に置く大型演算子
+const LARGE_OPERATORS: &[&str] = &[
+    "\\sum", "\\prod", "\\coprod", "\\int", "\\oint", "\\bigcup", "\\bigcap", "\\lim", "\\max",
+    "\\min", "\\sup", "\\inf",
+];
+
+/// MathML の `<math>` 要素を Markdown の数式（インラインは `$...$`、ディスプレイは `$$...$$`）にする。
+///
+/// TeX の注釈（`<annotation encoding="application/x-tex">`）があればそれを使い、
+/// なければ MathML から TeX に変換する。テーブルのセル内ではセル区切りと紛れないよう
+/// `|` を `\vert` にし、1 行のインライン数式にする。
+pub fn math_markdown(math: &Rc<Node>) -> Option<String> {
+    let tex = tex_annotation(math).unwrap_or_else(|| mathml_to_tex(math));
+    let tex = strip_style_wrapper(tex.trim());
+    if tex.is_empty() {
+        return None;
+    }
+    let display = attribute(math, "display").as_deref() == Some("block")
+        || attribute(math, "mode").as_deref() == Some("display");
+
+    let in_table = std::iter::successors(parent_node(math), parent_node)
+        .any(|node| matches!(tag_name(&node), Some("td" | "th")));
+    if in_table {
+        let tex = tex.replace("\\|", "\\Vert ").replace('|', "\\vert ");
+        return Some(format!("${}$", single_line(&tex)));
+    }
+    if display {
+        let lines: Vec<&str> = tex.lines().filter(|line| !line.trim().is_empty()).collect();
+        Some(format!("\n\n$$\n{}\n$$\n\n", lines.join("\n")))
+    } else {
+        Some(format!("${}$", single_line(tex)))
+    }
+}
+
+/// 改行と連続する空白を 1 つの空白にまとめる
+fn single_line(tex: &str) -> String {
+    tex.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Wikipedia などが付ける `{\displaystyle ...}` / `{\textstyle ...}` を外す
+fn strip_style_wrapper(tex: &str) -> &str {
+    ["{\\displaystyle ", "{\\textstyle "]
+        .iter()
+        .find_map(|prefix| tex.strip_prefix(prefix)?.strip_suffix('}'))
+        .map_or(tex, str::trim)
+}
+
+/// 子孫の TeX の注釈
+fn tex_annotation(node: &Rc<Node>) -> Option<String> {
+    if tag_name(node) == Some("annotation")
+        && attribute(node, "encoding").is_some_and(|e| e.eq_ignore_ascii_case("application/x-tex"))
+    {
+        return Some(text(node));
+    }
+    node.children.borrow().iter().find_map(tex_annotation)
+}
+
+/// MathML を TeX に変換する
+fn mathml_to_tex(node: &Rc<Node>) -> String {
+    let children = element_children(node);
+    let child = |i: usize| children.get(i).map(mathml_to_tex).unwrap_or_default();
+    match tag_name(node).unwrap_or_default() {
+        "semantics" | "maction" => child(0),
+        "annotation" | "annotation-xml" | "none" | "mprescripts" => String::new(),
+        "mi" => identifier(&text(node)),
+        "mn" => text(node),
+        "mo" => operator(text(node).trim()),
+        "mtext" | "ms" => {
+            let t = text(node);
+            if t.trim().is_empty() {
+                String::new()
+            } else {
+                format!("\\text{{{}}}", t.replace('{', "\\{").replace('}', "\\}"))
+            }
+        }
+        "mspace" => "\\ ".to_string(),
+        "mfrac" => format!("\\frac{{{}}}{{{}}}", child(0), child(1)),
+        "msqrt" => format!("\\sqrt{{{}}}", concat(&children)),
+        "mroot" => format!("\\sqrt[{}]{{{}}}", child(1), child(0)),
+        "msup" => format!("{}^{{{}}}", base(&child(0)), child(1)),
+        "msub" => format!("{}_{{{}}}", base(&child(0)), child(1)),
+        "msubsup" | "munderover" => {
+            format!("{}_{{{}}}^{{{}}}", base(&child(0)), child(1), child(2))
+        }
+        "mover" => over(&child(0), &child(1)),
+        "munder" => under(&child(0), &child(1)),
+        "mtable" => {
+            let rows: Vec<String> = children
+                .iter()
+                .map(|row| {
+                    let cells: Vec<String> = element_children(row)
+                        .iter()
+                        .filter(|cell| tag_name(cell) == Some("mtd"))
+                        .map(mathml_to_tex)
+                        .collect();
+                    cells.join(" & ")
+                })
+                .collect();
+            format!("\\begin{{matrix}} {} \\end{{matrix}}", rows.join(" \\\\ "))
+        }
+        "mfenced" => {
+            let open = attribute(node, "open").unwrap_or_else(|| "(".to_string());
+            let close = attribute(node, "close").unwrap_or_else(|| ")".to_string());
+            let separator = attribute(node, "separators").unwrap_or_else(|| ",".to_string());
+            let items: Vec<String> = children.iter().map(mathml_to_tex).collect();
+            format!(
+                "\\left{} {} \\right{}",
+                fence(&open),
+                items.join(separator.trim()),
+                fence(&close)
+            )
+        }
+        _ => concat(&children),
+    }
+}
+
+/// 子要素の TeX を連結する（制御綴の直後に英字が続く場合は空白を挟む）
+fn concat(children: &[Rc<Node>]) -> String {
+    let mut out = String::new();
+    for child in children {
+        let tex = mathml_to_tex(child);
+        if ends_with_control_word(&out) && tex.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            out.push(' ');
+        }
+        out.push_str(&tex);
+    }
+    out
+}
+
+fn ends_with_control_word(tex: &str) -> bool {
+    let rest = tex.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    rest.len() < tex.len() && rest.ends_with('\\')
+}
+
+/// 添字の土台（1 文字か 1 つの制御綴でなければ `{}` で囲む）
+fn base(tex: &str) -> String {
+    let single = tex.chars().count() == 1
+        || tex
+            .strip_prefix('\\')
+            .is_some_and(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphabetic()));
+    if single {
+        tex.to_string()
+    } else {
+        format!("{{{tex}}}")
+    }
+}
+
+fn over(base_tex: &str, over_tex: &str) -> String {
+    let accent = match over_tex {
+        "^" | "\u{2c6}" | "\u{302}" => Some("\\hat"),
+        "\u{af}" | "\u{203e}" | "\u{305}" | "-" | "\\_" => Some("\\overline"),
+        "\\to" | "\u{20d7}" => Some("\\vec"),
+        "~" | "\\sim" | "\u{2dc}" | "\u{303}" => Some("\\tilde"),
+        "\u{2d9}" | "." | "\u{307}" => Some("\\dot"),
+        "\u{a8}" | "\u{308}" => Some("\\ddot"),
+        "\u{23de}" => Some("\\overbrace"),
+        _ => None,
+    };
+    match accent {
+        Some(accent) => format!("{accent}{{{base_tex}}}"),
+        None if LARGE_OPERATORS.contains(&base_tex) => format!("{base_tex}^{{{over_tex}}}"),
+        None => format!("\\overset{{{over_tex}}}{{{base_tex}}}"),
+    }
+}
+
+fn under(base_tex: &str, under_tex: &str) -> String {
+    match under_tex {
+        "\\_" | "\u{332}" | "\u{af}" | "-" => format!("\\underline{{{base_tex}}}"),
+        "\u{23df}" => format!("\\underbrace{{{base_tex}}}"),
+        _ if LARGE_OPERATORS.contains(&base_tex) => format!("{base_tex}_{{{under_tex}}}"),
+        _ => format!("\\underset{{{under_tex}}}{{{base_tex}}}"),
+    }
+}
+
+/// `\left` / `\right` に続ける括弧
+fn fence(delimiter: &str) -> String {
+    match delimiter.trim() {
+        "" => ".".to_string(),
+        "{" => "\\{".to_string(),
+        "}" => "\\}".to_string(),
+        d => operator(d),
+    }
+}
+
+/// 識別子（`<mi>`）
+fn identifier(name: &str) -> String {
+    let name = name.trim();
+    if name.chars().count() == 1 {
+        return symbol(name).unwrap_or(name).to_string();
+    }
+    if FUNCTIONS.contains(&name) {
+        format!("\\{name}")
+    } else if name.is_empty() {
+        String::new()
+    } else {
+        format!("\\mathrm{{{name}}}")
+    }
+}
+
+/// 演算子（`<mo>`）
+fn operator(op: &str) -> String {
+    match op {
+        // 関数適用・不可視の積などの不可視演算子
+        "\u{2061}" | "\u{2062}" | "\u{2063}" | "\u{2064}" => String::new(),
+        "{" | "}" | "#" | "%" | "&" | "$" | "_" => format!("\\{op}"),
+        _ => symbol(op).unwrap_or(op).to_string(),
+    }
+}
+
+/// Unicode の記号に対応する TeX の命令
+fn symbol(c: &str) -> Option<&'static str> {
+    Some(match c {
+        "α" => "\\alpha",
+        "β" => "\\beta",
+        "γ" => "\\gamma",
+        "δ" => "\\delta",
+        "ϵ" => "\\epsilon",
+        "ε" => "\\varepsilon",
+        "ζ" => "\\zeta",
+        "η" => "\\eta",
+        "θ" => "\\theta",
+        "ϑ" => "\\vartheta",
+        "ι" => "\\iota",
+        "κ" => "\\kappa",
+        "λ" => "\\lambda",
+        "μ" => "\\mu",
+        "ν" => "\\nu",
+        "ξ" => "\\xi",
+        "π" => "\\pi",
+        "ρ" => "\\rho",
+        "σ" => "\\sigma",
+        "ς" => "\\varsigma",
+        "τ" => "\\tau",
+        "υ" => "\\upsilon",
+        "ϕ" => "\\phi",
+        "φ" => "\\varphi",
+        "χ" => "\\chi",
+        "ψ" => "\\psi",
+        "ω" => "\\omega",
+        "Γ" => "\\Gamma",
+        "Δ" => "\\Delta",
+        "Θ" => "\\Theta",
+        "Λ" => "\\Lambda",
+        "Ξ" => "\\Xi",
+        "Π" => "\\Pi",
+        "Σ" => "\\Sigma",
+        "Υ" => "\\Upsilon",
+        "Φ" => "\\Phi",
+        "Ψ" => "\\Psi",
+        "Ω" => "\\Omega",
+        "∞" => "\\infty",
+        "∂" => "\\partial",
+        "∇" => "\\nabla",
+        "ℓ" => "\\ell",
+        "ℏ" => "\\hbar",
+        "∅" => "\\emptyset",
+        "±" => "\\pm",
+        "∓" => "\\mp",
+        "×" => "\\times",
+        "÷" => "\\div",
+        "⋅" | "·" => "\\cdot",
+        "∘" => "\\circ",
+        "∗" => "*",
+        "−" => "-",
+        "≤" => "\\le",
+        "≥" => "\\ge",
+        "≠" => "\\ne",
+        "≈" => "\\approx",
+        "≡" => "\\equiv",
+        "∼" => "\\sim",
+        "≃" => "\\simeq",
+        "≅" => "\\cong",
+        "∝" => "\\propto",
+        "≪" => "\\ll",
+        "≫" => "\\gg",
+        "→" => "\\to",
+        "←" => "\\leftarrow",
+        "↔" => "\\leftrightarrow",
+        "⇒" => "\\Rightarrow",
+        "⇐" => "\\Leftarrow",
+        "⇔" => "\\Leftrightarrow",
+        "↦" => "\\mapsto",
+        "∈" => "\\in",
+        "∉" => "\\notin",
+        "∋" => "\\ni",
+        "⊂" => "\\subset",
+        "⊆" => "\\subseteq",
+        "⊃" => "\\supset",
+        "⊇" => "\\supseteq",
+        "∪" => "\\cup",
+        "∩" => "\\cap",
+        "∖" => "\\setminus",
+        "∧" => "\\wedge",
+        "∨" => "\\vee",
+        "¬" => "\\neg",
+        "∀" => "\\forall",
+        "∃" => "\\exists",
+        "⊗" => "\\otimes",
+        "⊕" => "\\oplus",
+        "∑" => "\\sum",
+        "∏" => "\\prod",
+        "∐" => "\\coprod",
+        "∫" => "\\int",
+        "∮" => "\\oint",
+        "⋃" => "\\bigcup",
+        "⋂" => "\\bigcap",
+        "√" => "\\surd",
+        "⟨" => "\\langle",
+        "⟩" => "\\rangle",
+        "⌊" => "\\lfloor",
+        "⌋" => "\\rfloor",
+        "⌈" => "\\lceil",
+        "⌉" => "\\rceil",
+        "∥" | "‖" => "\\|",
+        "…" => "\\ldots",
+        "⋯" => "\\cdots",
+        "′" => "'",
+        "″" => "''",
+        _ => return None,
+    })
+}
+
+/// 空白のテキストを除いた子要素
+fn element_children(node: &Node) -> Vec<Rc<Node>> {
+    node.children
+        .borrow()
+        .iter()
+        .filter(|child| matches!(child.data, NodeData::Element { .. }))
+        .cloned()
+        .collect()
+}
+
+/// 子孫のテキストの連結
+fn text(node: &Node) -> String {
+    match &node.data {
+        NodeData::Text { contents } => contents.borrow().to_string(),
+        _ => node.children.borrow().iter().map(|c| text(c)).collect(),
+    }
+}
+
+fn attribute(node: &Node, name: &str) -> Option<String> {
+    let NodeData::Element { attrs, .. } = &node.data else {
+        return None;
+    };
+    attrs
+        .borrow()
+        .iter()
+        .find(|attr| &*attr.name.local == name)
+        .map(|attr| attr.value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::convert::ConvertOptions;
+
+    fn convert(html: &str) -> String {
+        ConvertOptions::default().converter().convert(html).unwrap()
+    }
+
+    // math_markdown のテスト
+
+    #[test]
+    fn uses_tex_annotation() {
+        // KaTeX の出力（ページ側で .katex-html を取り除いた後）
+        assert_eq!(
+            convert(
+                r#"<p>Euler: <math><semantics><mrow><mi>e</mi></mrow><annotation encoding="application/x-tex">e^{i\pi} + 1 = 0</annotation></semantics></math>.</p>"#
+            ),
+            r"Euler: $e^{i\pi} + 1 = 0$."
+        );
+    }
+
+    #[test]
+    fn display_math_is_a_block() {
+        assert_eq!(
+            convert(
+                r#"<p>a</p><math display="block"><semantics><mi>x</mi><annotation encoding="application/x-tex">\sum_{i=1}^n |x_i|
+</annotation></semantics></math><p>b</p>"#
+            ),
+            "a\n\n$$\n\\sum_{i=1}^n |x_i|\n$$\n\nb"
+        );
+    }
+
+    #[test]
+    fn strips_displaystyle_wrapper() {
+        assert_eq!(
+            convert(
+                r#"<math alttext="{\displaystyle x}"><semantics><mi>x</mi><annotation encoding="application/x-tex">{\displaystyle x_{1}}</annotation></semantics></math>"#
+            ),
+            "$x_{1}$"
+        );
+    }
+
+    #[test]
+    fn converts_mathml_without_annotation() {
+        assert_eq!(
+            convert(
+                "<math><mfrac><mrow><mo>-</mo><mi>b</mi><mo>±</mo><msqrt><msup><mi>b</mi><mn>2</mn></msup><mo>−</mo><mn>4</mn><mi>a</mi><mi>c</mi></msqrt></mrow><mrow><mn>2</mn><mi>a</mi></mrow></mfrac></math>"
+            ),
+            r"$\frac{-b\pm\sqrt{b^{2}-4ac}}{2a}$"
+        );
+        assert_eq!(
+            convert(
+                "<math><munderover><mo>∑</mo><mrow><mi>i</mi><mo>=</mo><mn>1</mn></mrow><mi>n</mi></munderover><msub><mi>α</mi><mi>i</mi></msub><mo>⁢</mo><mi>sin</mi><mo>⁡</mo><mi>x</mi></math>"
+            ),
+            r"$\sum_{i=1}^{n}\alpha_{i}\sin x$"
+        );
+        assert_eq!(
+            convert("<math><mover><mi>v</mi><mo>→</mo></mover><mtext>speed</mtext></math>"),
+            r"$\vec{v}\text{speed}$"
+        );
+    }
+
+    #[test]
+    fn math_in_table_cell_avoids_pipes() {
+        let md = convert(
+            r#"<table><tr><th>f</th></tr><tr><td><math><semantics><mi>x</mi><annotation encoding="application/x-tex">|x| \| y</annotation></semantics></math></td></tr></table>"#,
+        );
+        assert!(md.contains(r"| $\vert x\vert \Vert y$ |"), "{md}");
+    }
+
+    #[test]
+    fn tex_is_not_escaped() {
+        assert_eq!(
+            convert(
+                r#"<p><math><semantics><mi>a</mi><annotation encoding="application/x-tex">a_1 * b_2 [c]</annotation></semantics></math></p>"#
+            ),
+            "$a_1 * b_2 [c]$"
+        );
+    }
+}
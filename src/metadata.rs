@@ -0,0 +1,301 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use headless_chrome::Tab;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// ページのメタデータの候補を JSON 文字列で返す JavaScript
+const METADATA_JS: &str = include_str!("js/metadata.js");
+
+/// フロントマターの形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FrontMatter {
+    /// `---` で囲んだ YAML
+    Yaml,
+    /// `+++` で囲んだ TOML
+    Toml,
+}
+
+/// ページから集めたメタデータの候補
+#[derive(Debug, Default, Deserialize)]
+struct RawMetadata {
+    fetched_at: Option<String>,
+    title: Option<String>,
+    og_title: Option<String>,
+    description: Option<String>,
+    og_description: Option<String>,
+    canonical: Option<String>,
+    og_url: Option<String>,
+    lang: Option<String>,
+    og_locale: Option<String>,
+    author: Option<String>,
+    article_author: Option<String>,
+    published: Option<String>,
+    #[serde(default)]
+    jsonld: Vec<Value>,
+}
+
+/// フロントマターに書き出すページのメタデータ（フィールド順が出力順）
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Metadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonical: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetched_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published: Option<String>,
+}
+
+/// JSON-LD から取り出した値
+#[derive(Debug, Default)]
+struct LinkedData {
+    headline: Option<String>,
+    description: Option<String>,
+    language: Option<String>,
+    author: Option<String>,
+    published: Option<String>,
+}
+
+impl LinkedData {
+    /// JSON-LD のノード（`@graph` や配列を含む）を順にたどり、最初に見つかった値を採る
+    fn collect(value: &Value, out: &mut LinkedData) {
+        match value {
+            Value::Array(items) => items.iter().for_each(|item| Self::collect(item, out)),
+            Value::Object(map) => {
+                let text = |key: &str| {
+                    map.get(key)
+                        .and_then(Value::as_str)
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(str::to_string)
+                };
+                out.headline = out.headline.take().or_else(|| text("headline"));
+                out.description = out.description.take().or_else(|| text("description"));
+                out.language = out.language.take().or_else(|| text("inLanguage"));
+                out.published = out.published.take().or_else(|| text("datePublished"));
+                if out.author.is_none() {
+                    out.author = map.get("author").and_then(author_names);
+                }
+                if let Some(graph) = map.get("@graph") {
+                    Self::collect(graph, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// JSON-LD の `author`（文字列・Person・その配列）を名前の列にする
+fn author_names(value: &Value) -> Option<String> {
+    let names: Vec<&str> = match value {
+        Value::Array(items) => items.iter().filter_map(author_name).collect(),
+        other => author_name(other).into_iter().collect(),
+    };
+    (!names.is_empty()).then(|| names.join(", "))
+}
+
+fn author_name(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(name) => Some(name.as_str()),
+        Value::Object(map) => map.get("name").and_then(Value::as_str),
+        _ => None,
+    }
+    .map(str::trim)
+    .filter(|name| !name.is_empty())
+}
+
+impl Metadata {
+    /// 候補から優先順位に従って値を選ぶ
+    fn from_raw(raw: RawMetadata, source: &str) -> Self {
+        let mut ld = LinkedData::default();
+        LinkedData::collect(&Value::Array(raw.jsonld), &mut ld);
+        // article:author は著者ページの URL であることが多い
+        let article_author = raw.article_author.filter(|a| !a.starts_with("http"));
+        Metadata {
+            title: raw.og_title.or(ld.headline).or(raw.title),
+            source: source.to_string(),
+            canonical: raw.canonical.or(raw.og_url),
+            fetched_at: raw.fetched_at,
+            description: raw.description.or(raw.og_description).or(ld.description),
+            language: raw
+                .lang
+                .or(ld.language)
+                .or_else(|| raw.og_locale.map(|l| l.replace('_', "-"))),
+            author: raw.author.or(article_author).or(ld.author),
+            published: raw.published.or(ld.published),
+        }
+    }
+
+    /// フロントマターのブロック（区切り行を含む）として書き出す
+    pub fn to_front_matter(&self, format: FrontMatter) -> Result<String> {
+        match format {
+            FrontMatter::Yaml => {
+                // JSON の文字列リテラルは YAML のダブルクォート文字列としても有効
+                let value = serde_json::to_value(self)?;
+                let mut out = String::from("---\n");
+                for (key, value) in value.as_object().into_iter().flatten() {
+                    out.push_str(&format!("{key}: {value}\n"));
+                }
+                out.push_str("---\n");
+                Ok(out)
+            }
+            FrontMatter::Toml => {
+                let body = toml::to_string(self).context("Failed to serialize front matter")?;
+                Ok(format!("+++\n{body}+++\n"))
+            }
+        }
+    }
+}
+
+/// 読み込み済みのページからメタデータを集める
+pub fn collect_metadata(tab: &Tab, source: &str) -> Result<Metadata> {
+    let result = tab
+        .evaluate(METADATA_JS, false)
+        .context("Failed to collect page metadata")?;
+    let raw = match result.value.as_ref().and_then(|v| v.as_str()) {
+        Some(json) => serde_json::from_str(json).context("Invalid page metadata")?,
+        None => RawMetadata::default(),
+    };
+    Ok(Metadata::from_raw(raw, source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn raw(value: Value) -> RawMetadata {
+        serde_json::from_value(value).unwrap()
+    }
+
+    // Metadata::from_raw のテスト
+
+    #[test]
+    fn from_raw_prefers_meta_tags() {
+        let metadata = Metadata::from_raw(
+            raw(json!({
+                "fetched_at": "2026-01-02T03:04:05.000Z",
+                "title": "Page - Site",
+                "og_title": "Page",
+                "description": "Desc",
+                "og_description": "OG desc",
+                "canonical": "https://example.com/page",
+                "og_url": "https://example.com/og",
+                "lang": "ja",
+                "author": "Alice",
+                "published": "2026-01-01",
+                "jsonld": [{"headline": "LD", "author": {"name": "Bob"}}],
+            })),
+            "https://example.com/page?utm=1",
+        );
+        assert_eq!(
+            metadata,
+            Metadata {
+                title: Some("Page".to_string()),
+                source: "https://example.com/page?utm=1".to_string(),
+                canonical: Some("https://example.com/page".to_string()),
+                fetched_at: Some("2026-01-02T03:04:05.000Z".to_string()),
+                description: Some("Desc".to_string()),
+                language: Some("ja".to_string()),
+                author: Some("Alice".to_string()),
+                published: Some("2026-01-01".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn from_raw_falls_back_to_json_ld() {
+        let metadata = Metadata::from_raw(
+            raw(json!({
+                "title": "Doc title",
+                "jsonld": [{
+                    "@context": "https://schema.org",
+                    "@graph": [
+                        {"@type": "WebSite", "name": "Site"},
+                        {
+                            "@type": "Article",
+                            "headline": "Headline",
+                            "description": "LD desc",
+                            "inLanguage": "en-US",
+                            "datePublished": "2025-12-31T00:00:00Z",
+                            "author": [{"@type": "Person", "name": "Bob"}, "Carol"],
+                        },
+                    ],
+                }],
+            })),
+            "https://example.com/",
+        );
+        assert_eq!(metadata.title.as_deref(), Some("Headline"));
+        assert_eq!(metadata.description.as_deref(), Some("LD desc"));
+        assert_eq!(metadata.language.as_deref(), Some("en-US"));
+        assert_eq!(metadata.author.as_deref(), Some("Bob, Carol"));
+        assert_eq!(metadata.published.as_deref(), Some("2025-12-31T00:00:00Z"));
+    }
+
+    #[test]
+    fn from_raw_uses_document_title_and_og_fallbacks() {
+        let metadata = Metadata::from_raw(
+            raw(json!({
+                "title": "Doc title",
+                "og_url": "https://example.com/og",
+                "og_locale": "en_GB",
+                "article_author": "https://example.com/authors/alice",
+            })),
+            "https://example.com/",
+        );
+        assert_eq!(metadata.title.as_deref(), Some("Doc title"));
+        assert_eq!(
+            metadata.canonical.as_deref(),
+            Some("https://example.com/og")
+        );
+        assert_eq!(metadata.language.as_deref(), Some("en-GB"));
+        assert_eq!(metadata.author, None);
+    }
+
+    // Metadata::to_front_matter のテスト
+
+    fn sample() -> Metadata {
+        Metadata {
+            title: Some("Say \"hi\": a guide".to_string()),
+            source: "https://example.com/a".to_string(),
+            author: Some("Alice\nBob".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn yaml_front_matter_quotes_values() {
+        assert_eq!(
+            sample().to_front_matter(FrontMatter::Yaml).unwrap(),
+            "---\n\
+             title: \"Say \\\"hi\\\": a guide\"\n\
+             source: \"https://example.com/a\"\n\
+             author: \"Alice\\nBob\"\n\
+             ---\n"
+        );
+    }
+
+    #[test]
+    fn toml_front_matter() {
+        assert_eq!(
+            sample().to_front_matter(FrontMatter::Toml).unwrap(),
+            "+++\n\
+             title = 'Say \"hi\": a guide'\n\
+             source = \"https://example.com/a\"\n\
+             author = \"\"\"\n\
+             Alice\n\
+             Bob\"\"\"\n\
+             +++\n"
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use anyhow::{Context, Result};
+use headless_chrome::Tab;
+
+/// Chrome が `<pre>` に包んで表示した本文（テキスト・JSON）を取り出す JavaScript
+const BODY_JS: &str = r#"(() => {
+    const pre = document.querySelector('body > pre');
+    const root = pre || document.body || document.documentElement;
+    return root ? root.textContent : '';
+})()"#;
+
+/// 変換せずに出力する文書の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlainKind {
+    /// プレーンテキスト（そのまま出力）
+    Text,
+    /// Markdown（そのまま出力し、`--format text` ではテキストにする）
+    Markdown,
+    /// JSON（整形して出力）
+    Json,
+}
+
+/// `document.contentType` から、変換せずに出力する文書かを判定する
+pub fn classify(content_type: &str) -> Option<PlainKind> {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    match mime.as_str() {
+        "text/plain" => Some(PlainKind::Text),
+        "text/markdown" | "text/x-markdown" => Some(PlainKind::Markdown),
+        "application/json" | "text/json" => Some(PlainKind::Json),
+        _ if mime.starts_with("application/") && mime.ends_with("+json") => Some(PlainKind::Json),
+        _ => None,
+    }
+}
+
+/// 本文を出力用に整える。JSON は整形し、解釈できなければそのまま返す。
+pub fn render(kind: PlainKind, body: &str) -> String {
+    match kind {
+        PlainKind::Text | PlainKind::Markdown => body.to_string(),
+        PlainKind::Json => serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|value| serde_json::to_string_pretty(&value).ok())
+            .unwrap_or_else(|| body.to_string()),
+    }
+}
+
+/// HTML 以外の文書なら、その MIME タイプ・種類と出力用の本文を返す
+pub fn read_plain_document(tab: &Tab) -> Result<Option<(String, PlainKind, String)>> {
+    let content_type = tab
+        .evaluate("document.contentType", false)
+        .context("Failed to read document content type")?
+        .value
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let Some(kind) = classify(&content_type) else {
+        return Ok(None);
+    };
+    let body = tab
+        .evaluate(BODY_JS, false)
+        .context("Failed to read document body")?
+        .value
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default();
+    Ok(Some((content_type, kind, render(kind, &body))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // classify のテスト
+
+    #[test]
+    fn classify_text_types() {
+        assert_eq!(classify("text/plain"), Some(PlainKind::Text));
+        assert_eq!(
+            classify("text/markdown; charset=utf-8"),
+            Some(PlainKind::Markdown)
+        );
+        assert_eq!(classify("Text/X-Markdown"), Some(PlainKind::Markdown));
+    }
+
+    #[test]
+    fn classify_json_types() {
+        assert_eq!(classify("application/json"), Some(PlainKind::Json));
+        assert_eq!(classify("application/ld+json"), Some(PlainKind::Json));
+        assert_eq!(classify("text/json"), Some(PlainKind::Json));
+    }
+
+    #[test]
+    fn classify_html_and_others() {
+        assert_eq!(classify("text/html"), None);
+        assert_eq!(classify("application/xhtml+xml"), None);
+        assert_eq!(classify("image/svg+xml"), None);
+        assert_eq!(classify(""), None);
+    }
+
+    // render のテスト
+
+    #[test]
+    fn render_text_as_is() {
+        assert_eq!(
+            render(PlainKind::Text, "# A\n\n```\nx\n```\n"),
+            "# A\n\n```\nx\n```\n"
+        );
+    }
+
+    #[test]
+    fn render_json_pretty_keeps_key_order() {
+        assert_eq!(
+            render(PlainKind::Json, r#"{"b":1,"a":[true,null]}"#),
+            "{\n  \"b\": 1,\n  \"a\": [\n    true,\n    null\n  ]\n}"
+        );
+    }
+
+    #[test]
+    fn render_invalid_json_as_is() {
+        assert_eq!(render(PlainKind::Json, "{not json"), "{not json");
+    }
+}
@@ -0,0 +1,345 @@
+use std::fmt;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// ドキュメント生成ツール向けの組み込みプリセット
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    /// meta タグや DOM の目印から生成ツールを判定する
+    Auto,
+    Docusaurus,
+    MkdocsMaterial,
+    Sphinx,
+    Vitepress,
+    Gitbook,
+    Mintlify,
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_possible_value() {
+            Some(value) => f.write_str(value.get_name()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// プリセットが適用する抽出ルール
+#[derive(Debug, PartialEq, Eq)]
+pub struct Rules {
+    /// 本文のセレクタ（`||` 区切りの代替指定）
+    pub selector: &'static str,
+    /// 本文から取り除くノイズのセレクタ
+    pub exclude: &'static [&'static str],
+    /// 抽出前に出現を待つ要素のセレクタ
+    pub wait_for: Option<&'static str>,
+}
+
+/// 自動判定の手がかり
+struct Signature {
+    preset: Preset,
+    /// `<meta name="generator">` に含まれる語（小文字）
+    generators: &'static [&'static str],
+    /// ページに存在すれば生成ツールを示す要素のセレクタ
+    markers: &'static [&'static str],
+}
+
+/// 自動判定で照合する順序（より具体的なものを先に置く）
+const SIGNATURES: &[Signature] = &[
+    Signature {
+        preset: Preset::Docusaurus,
+        generators: &["docusaurus"],
+        markers: &["#__docusaurus", ".theme-doc-markdown"],
+    },
+    Signature {
+        preset: Preset::MkdocsMaterial,
+        generators: &["mkdocs-material"],
+        markers: &["[data-md-component=\"content\"]", ".md-content__inner"],
+    },
+    Signature {
+        preset: Preset::Vitepress,
+        generators: &["vitepress"],
+        markers: &["#VPContent", ".VPDoc"],
+    },
+    Signature {
+        preset: Preset::Mintlify,
+        generators: &["mintlify"],
+        markers: &["#content-area[data-mdx-content]", "#content-area"],
+    },
+    Signature {
+        preset: Preset::Gitbook,
+        generators: &["gitbook"],
+        markers: &["[data-testid=\"page.contentEditor\"]", ".gitbook-root"],
+    },
+    Signature {
+        preset: Preset::Sphinx,
+        generators: &["sphinx", "docutils"],
+        markers: &[
+            "#documentation_options",
+            "script[src*=\"documentation_options\"]",
+            ".sphinxsidebar",
+            ".rst-content",
+        ],
+    },
+];
+
+impl Preset {
+    /// プリセットの抽出ルール（`Auto` は判定後に決まるため `None`）
+    pub fn rules(self) -> Option<Rules> {
+        let rules = match self {
+            Preset::Auto => return None,
+            Preset::Docusaurus => Rules {
+                selector: ".theme-doc-markdown || article || main",
+                exclude: &[
+                    ".hash-link",
+                    ".theme-edit-this-page",
+                    ".theme-doc-breadcrumbs",
+                    ".theme-doc-toc-mobile",
+                    ".theme-doc-footer",
+                    ".pagination-nav",
+                ],
+                wait_for: Some(".theme-doc-markdown"),
+            },
+            Preset::MkdocsMaterial => Rules {
+                selector: ".md-content__inner || article || main",
+                exclude: &[
+                    ".headerlink",
+                    ".md-content__button",
+                    ".md-source-file",
+                    ".md-feedback",
+                    ".md-clipboard",
+                ],
+                wait_for: Some(".md-content"),
+            },
+            Preset::Sphinx => Rules {
+                selector: "div[role=\"main\"] || .body || .document",
+                exclude: &[
+                    ".headerlink",
+                    ".sphinxsidebar",
+                    ".related",
+                    ".rst-footer-buttons",
+                    ".prev-next-area",
+                    ".copybtn",
+                ],
+                wait_for: None,
+            },
+            Preset::Vitepress => Rules {
+                selector: ".vp-doc || main",
+                exclude: &[".header-anchor", ".edit-link", ".prev-next", ".copy"],
+                wait_for: Some(".vp-doc"),
+            },
+            Preset::Gitbook => Rules {
+                selector: "[data-testid=\"page.contentEditor\"] || main",
+                exclude: &[
+                    "[data-testid=\"page-footer-navigation\"]",
+                    "[aria-label=\"Direct link to heading\"]",
+                    "aside",
+                ],
+                wait_for: Some("main"),
+            },
+            Preset::Mintlify => Rules {
+                selector: "#content-area || main",
+                exclude: &[
+                    "#pagination",
+                    "#table-of-contents",
+                    ".feedback-toolbar",
+                    "[aria-label=\"Navigate to header\"]",
+                ],
+                wait_for: Some("#content-area"),
+            },
+        };
+        Some(rules)
+    }
+}
+
+/// 自動判定用にページから集めた情報
+#[derive(Debug, Default, Deserialize)]
+pub struct PageSignals {
+    /// `<meta name="generator">` の内容
+    pub generator: String,
+    /// ページに存在した目印のセレクタ
+    pub markers: Vec<String>,
+}
+
+/// 自動判定に必要な情報を JSON 文字列で返す JavaScript を生成する
+pub fn detection_script() -> String {
+    let markers: Vec<String> = SIGNATURES
+        .iter()
+        .flat_map(|s| s.markers)
+        .map(|m| crate::escape_js_string(m))
+        .collect();
+    format!(
+        r#"(() => {{
+            const generator = Array.from(document.querySelectorAll('meta[name="generator" i]'))
+                .map(meta => meta.content || '').join(' ');
+            const markers = [{}].filter(sel => document.querySelector(sel));
+            return JSON.stringify({{ generator, markers }});
+        }})()"#,
+        markers.join(", ")
+    )
+}
+
+/// 判定スクリプトの戻り値（JSON 文字列）を解釈する
+pub fn parse_signals(value: Option<&serde_json::Value>) -> Result<PageSignals> {
+    match value.and_then(|v| v.as_str()) {
+        Some(json) => serde_json::from_str(json).context("Invalid preset detection result"),
+        None => Ok(PageSignals::default()),
+    }
+}
+
+/// ページの情報から生成ツールを判定する。generator を目印より優先する。
+pub fn detect(signals: &PageSignals) -> Option<Preset> {
+    let generator = signals.generator.to_ascii_lowercase();
+    SIGNATURES
+        .iter()
+        .find(|s| s.generators.iter().any(|g| generator.contains(g)))
+        .or_else(|| {
+            SIGNATURES.iter().find(|s| {
+                s.markers
+                    .iter()
+                    .any(|m| signals.markers.iter().any(|x| x == m))
+            })
+        })
+        .map(|s| s.preset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selector::SelectorSpec;
+
+    fn signals(generator: &str, markers: &[&str]) -> PageSignals {
+        PageSignals {
+            generator: generator.to_string(),
+            markers: markers.iter().map(|m| m.to_string()).collect(),
+        }
+    }
+
+    // detect のテスト
+
+    #[test]
+    fn detect_from_generator() {
+        assert_eq!(
+            detect(&signals("Docusaurus v3.5.2", &[])),
+            Some(Preset::Docusaurus)
+        );
+        assert_eq!(
+            detect(&signals("mkdocs-1.6.0, mkdocs-material-9.5.30", &[])),
+            Some(Preset::MkdocsMaterial)
+        );
+        assert_eq!(
+            detect(&signals("VitePress v1.3.1", &[])),
+            Some(Preset::Vitepress)
+        );
+        assert_eq!(
+            detect(&signals(
+                "Docutils 0.18.1: http://docutils.sourceforge.net/",
+                &[]
+            )),
+            Some(Preset::Sphinx)
+        );
+    }
+
+    #[test]
+    fn detect_plain_mkdocs_is_not_material() {
+        assert_eq!(detect(&signals("mkdocs-1.6.0", &[])), None);
+    }
+
+    #[test]
+    fn detect_from_markers() {
+        assert_eq!(
+            detect(&signals("", &["#VPContent"])),
+            Some(Preset::Vitepress)
+        );
+        assert_eq!(
+            detect(&signals("", &[".sphinxsidebar"])),
+            Some(Preset::Sphinx)
+        );
+    }
+
+    #[test]
+    fn detect_generator_wins_over_markers() {
+        assert_eq!(
+            detect(&signals("GitBook", &["#content-area"])),
+            Some(Preset::Gitbook)
+        );
+    }
+
+    #[test]
+    fn detect_unknown_page() {
+        assert_eq!(detect(&signals("WordPress 6.6", &[])), None);
+        assert_eq!(detect(&PageSignals::default()), None);
+    }
+
+    // Preset::rules のテスト
+
+    #[test]
+    fn auto_has_no_rules() {
+        assert_eq!(Preset::Auto.rules(), None);
+    }
+
+    #[test]
+    fn every_preset_has_rules_with_fallback_selector() {
+        for preset in Preset::value_variants() {
+            if *preset == Preset::Auto {
+                continue;
+            }
+            let rules = preset.rules().unwrap();
+            let spec = SelectorSpec::parse(rules.selector);
+            assert!(spec.alternatives.len() >= 2, "{preset:?}");
+        }
+    }
+
+    #[test]
+    fn every_signature_preset_has_rules() {
+        for signature in SIGNATURES {
+            assert!(signature.preset.rules().is_some());
+        }
+    }
+
+    #[test]
+    fn preset_display_uses_value_name() {
+        assert_eq!(Preset::MkdocsMaterial.to_string(), "mkdocs-material");
+    }
+
+    #[test]
+    fn preset_value_names() {
+        assert_eq!(
+            Preset::from_str("mkdocs-material", false),
+            Ok(Preset::MkdocsMaterial)
+        );
+        assert_eq!(Preset::from_str("auto", false), Ok(Preset::Auto));
+    }
+
+    // detection_script / parse_signals のテスト
+
+    #[test]
+    fn detection_script_lists_all_markers() {
+        let js = detection_script();
+        for signature in SIGNATURES {
+            for marker in signature.markers {
+                assert!(js.contains(&crate::escape_js_string(marker)));
+            }
+        }
+    }
+
+    #[test]
+    fn parse_signals_reads_json_string() {
+        let value = serde_json::Value::String(
+            r##"{"generator":"VitePress","markers":["#VPContent"]}"##.to_string(),
+        );
+        let signals = parse_signals(Some(&value)).unwrap();
+        assert_eq!(signals.generator, "VitePress");
+        assert_eq!(signals.markers, vec!["#VPContent"]);
+    }
+
+    #[test]
+    fn parse_signals_without_value_is_empty() {
+        let signals = parse_signals(None).unwrap();
+        assert!(signals.generator.is_empty());
+        assert!(signals.markers.is_empty());
+    }
+}
@@ -5,11 +5,17 @@ use std::time::Duration;
 pub struct Progress {
     enabled: bool,
     bar: Option<ProgressBar>,
+    /// 表示した警告（JSON 出力に含める）
+    warnings: Vec<String>,
 }
 
 impl Progress {
     pub fn new(enabled: bool) -> Self {
-        Self { enabled, bar: None }
+        Self {
+            enabled,
+            bar: None,
+            warnings: Vec::new(),
+        }
     }
 
     /// メッセージ付きスピナーを表示する
@@ -59,6 +65,18 @@ impl Progress {
         bar.finish_with_message(format!("{icon} {message}"));
     }
 
+    /// 警告を標準エラーへ表示し、記録する（`--quiet` でも表示する）
+    pub fn warn(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        eprintln!("Warning: {message}");
+        self.warnings.push(message);
+    }
+
+    /// 記録した警告を取り出す
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
     /// 現在の進捗バーを完了して消去する
     pub fn finish_and_clear(&mut self) {
         if let Some(ref bar) = self.bar {
@@ -153,4 +171,13 @@ mod tests {
         p.finish("second done");
         assert!(p.bar.is_none());
     }
+
+    #[test]
+    fn warn_records_messages_even_when_disabled() {
+        let mut p = Progress::new(false);
+        p.warn("first");
+        p.warn(format!("second {}", 2));
+        assert_eq!(p.take_warnings(), vec!["first", "second 2"]);
+        assert!(p.take_warnings().is_empty());
+    }
 }
@@ -0,0 +1,266 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::metadata::Metadata;
+
+/// 複数ページをつなぐ区切り
+const PAGE_SEPARATOR: &str = "\n\n---\n\n";
+const TEXT_PAGE_SEPARATOR: &str = "\n\n";
+const HTML_PAGE_SEPARATOR: &str = "\n<hr>\n";
+
+/// 出力形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Markdown（複数ページは区切り線でつなぐ）
+    Markdown,
+    /// Markdown の記法を取り除いたテキスト
+    Text,
+    /// スクリプトを除き URL を解決した HTML 断片
+    Html,
+    /// ページごとの結果を JSON で出力する（複数ページは配列）
+    Json,
+    /// 1 行に 1 ページの JSON を出力する
+    Ndjson,
+}
+
+/// 1 ページの取得・変換結果
+#[derive(Debug, Default, Serialize)]
+pub struct PageReport {
+    /// 指定された URL
+    pub url: String,
+    /// リダイレクト後の URL
+    pub final_url: Option<String>,
+    pub title: Option<String>,
+    pub metadata: Option<Metadata>,
+    /// ページ全体の Markdown（フロントマターを含む）
+    pub markdown: String,
+    /// text / html 出力の本文
+    #[serde(skip)]
+    pub body: String,
+    /// セレクタごとの一致結果（サイトアダプタ・Markdown 版・テキスト文書では空）
+    pub selectors: Vec<SelectorReport>,
+    pub warnings: Vec<String>,
+    pub timings: Timings,
+    /// 取得・変換に失敗した場合のエラー（複数ページの実行で残りのページを続けたとき）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 1 つのセレクタ指定（`-s` / `--xpath` の値）の一致結果
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct SelectorReport {
+    /// 指定された元の文字列
+    pub selector: String,
+    /// 採用した代替セレクタ（一致しなければ `None`）
+    pub matched: Option<String>,
+    /// 重複除去・`--first` / `--nth` 適用前の一致件数
+    pub count: usize,
+    pub fragments: Vec<FragmentReport>,
+}
+
+/// 出力に含めた断片
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct FragmentReport {
+    pub markdown: String,
+    /// 変換前の HTML（`--include-html` 指定時のみ）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub html: Option<String>,
+}
+
+/// 処理時間（ミリ秒）
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Timings {
+    /// 遷移から追加待機の終了まで
+    pub load_ms: u64,
+    /// 抽出と Markdown への変換
+    pub convert_ms: u64,
+    /// ページ全体
+    pub total_ms: u64,
+}
+
+/// 各ページの結果を出力形式に従って書き出す。
+/// 失敗したページは JSON / NDJSON ではエラー付きで出力し、それ以外の形式では省く。
+pub fn render(format: OutputFormat, reports: &[PageReport]) -> Result<String> {
+    match format {
+        OutputFormat::Markdown => Ok(reports
+            .iter()
+            .filter(|r| r.error.is_none())
+            .map(|r| r.markdown.as_str())
+            .collect::<Vec<_>>()
+            .join(PAGE_SEPARATOR)),
+        OutputFormat::Text => Ok(join_bodies(reports, TEXT_PAGE_SEPARATOR)),
+        OutputFormat::Html => Ok(join_bodies(reports, HTML_PAGE_SEPARATOR)),
+        OutputFormat::Json => match reports {
+            [report] => serde_json::to_string_pretty(report),
+            reports => serde_json::to_string_pretty(reports),
+        }
+        .context("Failed to serialize result"),
+        OutputFormat::Ndjson => {
+            let mut out = String::new();
+            for report in reports {
+                out.push_str(&serde_json::to_string(report).context("Failed to serialize result")?);
+                out.push('\n');
+            }
+            Ok(out)
+        }
+    }
+}
+
+fn join_bodies(reports: &[PageReport], separator: &str) -> String {
+    reports
+        .iter()
+        .filter(|r| r.error.is_none())
+        .map(|r| r.body.as_str())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    fn report(url: &str, markdown: &str) -> PageReport {
+        PageReport {
+            url: url.to_string(),
+            markdown: markdown.to_string(),
+            ..Default::default()
+        }
+    }
+
+    // render のテスト
+
+    #[test]
+    fn render_markdown_joins_pages() {
+        let reports = [
+            report("https://a.test", "# A"),
+            report("https://b.test", "# B"),
+        ];
+        assert_eq!(
+            render(OutputFormat::Markdown, &reports).unwrap(),
+            "# A\n\n---\n\n# B"
+        );
+    }
+
+    #[test]
+    fn render_text_and_html_join_bodies() {
+        let mut a = report("https://a.test", "# A");
+        a.body = "A".to_string();
+        let mut b = report("https://b.test", "# B");
+        b.body = "B".to_string();
+        let reports = [a, b];
+        assert_eq!(render(OutputFormat::Text, &reports).unwrap(), "A\n\nB");
+        assert_eq!(render(OutputFormat::Html, &reports).unwrap(), "A\n<hr>\nB");
+    }
+
+    #[test]
+    fn render_json_omits_body() {
+        let mut page = report("https://a.test", "# A");
+        page.body = "A".to_string();
+        let text = render(OutputFormat::Json, &[page]).unwrap();
+        assert!(!text.contains("\"body\""));
+    }
+
+    #[test]
+    fn render_json_single_page_as_object() {
+        let mut page = report("https://a.test", "# A");
+        page.final_url = Some("https://a.test/".to_string());
+        page.selectors.push(SelectorReport {
+            selector: "article || main".to_string(),
+            matched: Some("main".to_string()),
+            count: 2,
+            fragments: vec![FragmentReport {
+                markdown: "# A".to_string(),
+                html: Some("<h1>A</h1>".to_string()),
+            }],
+        });
+        page.warnings
+            .push("no elements matched selector 'aside'".to_string());
+        let value: Value =
+            serde_json::from_str(&render(OutputFormat::Json, &[page]).unwrap()).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "url": "https://a.test",
+                "final_url": "https://a.test/",
+                "title": null,
+                "metadata": null,
+                "markdown": "# A",
+                "selectors": [{
+                    "selector": "article || main",
+                    "matched": "main",
+                    "count": 2,
+                    "fragments": [{"markdown": "# A", "html": "<h1>A</h1>"}],
+                }],
+                "warnings": ["no elements matched selector 'aside'"],
+                "timings": {"load_ms": 0, "convert_ms": 0, "total_ms": 0},
+            })
+        );
+    }
+
+    #[test]
+    fn render_json_omits_html_unless_requested() {
+        let mut page = report("https://a.test", "A");
+        page.selectors.push(SelectorReport {
+            selector: "p".to_string(),
+            fragments: vec![FragmentReport {
+                markdown: "A".to_string(),
+                html: None,
+            }],
+            ..Default::default()
+        });
+        let text = render(OutputFormat::Json, &[page]).unwrap();
+        assert!(!text.contains("\"html\""));
+    }
+
+    #[test]
+    fn render_json_multiple_pages_as_array() {
+        let reports = [report("https://a.test", "A"), report("https://b.test", "B")];
+        let value: Value =
+            serde_json::from_str(&render(OutputFormat::Json, &reports).unwrap()).unwrap();
+        assert_eq!(value.as_array().map(Vec::len), Some(2));
+        assert_eq!(value[1]["url"], "https://b.test");
+    }
+
+    #[test]
+    fn render_skips_failed_pages_in_documents() {
+        let mut failed = report("https://b.test", "");
+        failed.error = Some("Failed to navigate to URL".to_string());
+        let mut a = report("https://a.test", "# A");
+        a.body = "A".to_string();
+        let mut c = report("https://c.test", "# C");
+        c.body = "C".to_string();
+        let reports = [a, failed, c];
+        assert_eq!(
+            render(OutputFormat::Markdown, &reports).unwrap(),
+            "# A\n\n---\n\n# C"
+        );
+        assert_eq!(render(OutputFormat::Text, &reports).unwrap(), "A\n\nC");
+    }
+
+    #[test]
+    fn render_json_keeps_failed_pages_with_error() {
+        let mut failed = report("https://b.test", "");
+        failed.error = Some("Failed to navigate to URL".to_string());
+        let reports = [report("https://a.test", "A"), failed];
+        let value: Value =
+            serde_json::from_str(&render(OutputFormat::Json, &reports).unwrap()).unwrap();
+        assert!(value[0].get("error").is_none());
+        assert_eq!(value[1]["error"], "Failed to navigate to URL");
+    }
+
+    #[test]
+    fn render_ndjson_one_line_per_page() {
+        let reports = [
+            report("https://a.test", "A\nB"),
+            report("https://b.test", "C"),
+        ];
+        let text = render(OutputFormat::Ndjson, &reports).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(text.ends_with('\n'));
+        let first: Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["markdown"], "A\nB");
+    }
+}
@@ -0,0 +1,512 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::escape_js_string;
+
+/// XPath セレクタを示す接頭辞
+const XPATH_PREFIX: &str = "xpath:";
+
+/// 代替セレクタの区切り
+const FALLBACK_SEPARATOR: &str = "||";
+
+/// 抽出対象の要素を指定するセレクタ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector {
+    /// `document.querySelectorAll` で評価する CSS セレクタ
+    Css(String),
+    /// `document.evaluate` で評価する XPath 式
+    XPath(String),
+}
+
+impl Selector {
+    /// セレクタ文字列を解釈する。
+    ///
+    /// `xpath:` 接頭辞が付いていれば XPath、それ以外は CSS セレクタとして扱う。
+    pub fn parse(s: &str) -> Self {
+        match s.strip_prefix(XPATH_PREFIX) {
+            Some(expr) => Self::XPath(expr.trim_start().to_string()),
+            None => Self::Css(s.to_string()),
+        }
+    }
+
+    /// `root`（Document または ShadowRoot を指す JavaScript 式）配下で
+    /// 一致したノードの配列を返す JavaScript 式を生成する
+    pub fn nodes_js(&self, root: &str) -> String {
+        match self {
+            Self::Css(selector) => format!(
+                "Array.from({root}.querySelectorAll({}))",
+                escape_js_string(selector),
+            ),
+            Self::XPath(expr) => format!(
+                r#"(() => {{
+                    const snapshot = ({root}.ownerDocument || {root}).evaluate({}, {root}, null, XPathResult.ORDERED_NODE_SNAPSHOT_TYPE, null);
+                    const nodes = [];
+                    for (let i = 0; i < snapshot.snapshotLength; i++) {{
+                        nodes.push(snapshot.snapshotItem(i));
+                    }}
+                    return nodes;
+                }})()"#,
+                escape_js_string(expr),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Css(selector) => f.write_str(selector),
+            Self::XPath(expr) => write!(f, "{XPATH_PREFIX}{expr}"),
+        }
+    }
+}
+
+/// `||` で区切られた代替セレクタの並び。
+///
+/// 先頭から順に評価し、最初に一致したセレクタの結果を採用する。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorSpec {
+    /// 指定された元の文字列
+    pub raw: String,
+    /// 評価順の代替セレクタ
+    pub alternatives: Vec<Selector>,
+}
+
+impl SelectorSpec {
+    /// `-s` の値を解釈する（各代替は `Selector::parse` で解釈する）
+    pub fn parse(s: &str) -> Self {
+        Self::with(s, Selector::parse)
+    }
+
+    /// `--xpath` の値を解釈する（各代替を XPath 式として扱う）
+    pub fn parse_xpath(s: &str) -> Self {
+        Self::with(s, |alt| Selector::XPath(alt.to_string()))
+    }
+
+    fn with(s: &str, parse: impl Fn(&str) -> Selector) -> Self {
+        let alternatives = split_alternatives(s)
+            .into_iter()
+            .map(str::trim)
+            .filter(|alt| !alt.is_empty())
+            .map(parse)
+            .collect();
+        Self {
+            raw: s.to_string(),
+            alternatives,
+        }
+    }
+
+    /// 代替セレクタを持つ（フォールバック指定である）か
+    pub fn has_fallback(&self) -> bool {
+        self.alternatives.len() > 1
+    }
+}
+
+impl fmt::Display for SelectorSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+/// `--expect 'sel>=N'` で指定するセレクタの最小一致件数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expectation {
+    /// 対象セレクタ（`-s` / `--xpath` に指定した文字列）
+    pub selector: String,
+    /// 最小一致件数
+    pub min: usize,
+}
+
+impl FromStr for Expectation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (selector, min) = s
+            .rsplit_once(">=")
+            .ok_or_else(|| format!("expected 'SELECTOR>=N', got '{s}'"))?;
+        let selector = selector.trim();
+        if selector.is_empty() {
+            return Err(format!("missing selector in '{s}'"));
+        }
+        let min = min
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid minimum count in '{s}'"))?;
+        Ok(Self {
+            selector: selector.to_string(),
+            min,
+        })
+    }
+}
+
+/// セレクタの一致件数が `--strict` / `--expect` の条件を満たさなかったことを示すエラー
+#[derive(Debug, PartialEq, Eq)]
+pub struct SelectorMismatch {
+    /// 満たさなかった条件の説明
+    pub failures: Vec<String>,
+}
+
+impl fmt::Display for SelectorMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Selector expectations not met: {}",
+            self.failures.join("; ")
+        )
+    }
+}
+
+impl std::error::Error for SelectorMismatch {}
+
+/// `--expect` の対象セレクタがすべて指定済みのセレクタであることを確認する
+pub fn find_unknown_expectation<'a>(
+    specs: &[SelectorSpec],
+    expectations: &'a [Expectation],
+) -> Option<&'a Expectation> {
+    expectations
+        .iter()
+        .find(|e| !specs.iter().any(|spec| spec.raw == e.selector))
+}
+
+/// 各セレクタの一致件数（`counts[i]` が `specs[i]` に対応）を条件と照合する。
+///
+/// `strict` が有効なら一致しなかったセレクタをすべて失敗とする。
+pub fn check_match_counts(
+    specs: &[SelectorSpec],
+    counts: &[usize],
+    strict: bool,
+    expectations: &[Expectation],
+) -> Result<(), SelectorMismatch> {
+    let mut failures = Vec::new();
+
+    if strict {
+        for (spec, _) in specs.iter().zip(counts).filter(|(_, count)| **count == 0) {
+            failures.push(format!("no elements matched selector '{spec}'"));
+        }
+    }
+
+    for expectation in expectations {
+        let count = specs
+            .iter()
+            .zip(counts)
+            .find(|(spec, _)| spec.raw == expectation.selector)
+            .map_or(0, |(_, count)| *count);
+        if count < expectation.min {
+            failures.push(format!(
+                "selector '{}' matched {count} element(s), expected at least {}",
+                expectation.selector, expectation.min
+            ));
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(SelectorMismatch { failures })
+    }
+}
+
+/// クォート外の `||` で文字列を分割する
+fn split_alternatives(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0usize;
+    let mut quote: Option<char> = None;
+    let mut backslash_run = 0usize;
+    let mut chars = s.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            backslash_run += 1;
+            continue;
+        }
+        let escaped = backslash_run % 2 == 1;
+        backslash_run = 0;
+
+        match quote {
+            Some(q) if c == q && !escaped => quote = None,
+            Some(_) => {}
+            None if (c == '"' || c == '\'') && !escaped => quote = Some(c),
+            None if s[i..].starts_with(FALLBACK_SEPARATOR) => {
+                parts.push(&s[start..i]);
+                chars.next();
+                start = i + FALLBACK_SEPARATOR.len();
+            }
+            None => {}
+        }
+    }
+
+    parts.push(&s[start..]);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_css_selector() {
+        assert_eq!(
+            Selector::parse("article > h1"),
+            Selector::Css("article > h1".to_string()),
+        );
+    }
+
+    #[test]
+    fn parse_xpath_selector() {
+        assert_eq!(
+            Selector::parse("xpath://h2[contains(., 'Usage')]"),
+            Selector::XPath("//h2[contains(., 'Usage')]".to_string()),
+        );
+    }
+
+    #[test]
+    fn parse_xpath_selector_trims_leading_space() {
+        assert_eq!(
+            Selector::parse("xpath: //main"),
+            Selector::XPath("//main".to_string()),
+        );
+    }
+
+    #[test]
+    fn parse_prefix_is_case_sensitive() {
+        assert_eq!(
+            Selector::parse("XPATH://main"),
+            Selector::Css("XPATH://main".to_string()),
+        );
+    }
+
+    #[test]
+    fn display_round_trips() {
+        assert_eq!(Selector::parse("main").to_string(), "main");
+        assert_eq!(Selector::parse("xpath://main").to_string(), "xpath://main");
+    }
+
+    #[test]
+    fn css_nodes_js_uses_query_selector_all() {
+        assert_eq!(
+            Selector::Css("div.a".to_string()).nodes_js("document"),
+            r#"Array.from(document.querySelectorAll("div.a"))"#,
+        );
+    }
+
+    #[test]
+    fn css_nodes_js_uses_given_root() {
+        assert_eq!(
+            Selector::Css("p".to_string()).nodes_js("root"),
+            r#"Array.from(root.querySelectorAll("p"))"#,
+        );
+    }
+
+    #[test]
+    fn xpath_nodes_js_escapes_expression() {
+        let js = Selector::XPath(r#"//a[@title="x"]"#.to_string()).nodes_js("document");
+        assert!(js.contains(r#".evaluate("//a[@title=\"x\"]", document"#));
+        assert!(js.contains("ORDERED_NODE_SNAPSHOT_TYPE"));
+    }
+
+    // SelectorSpec のテスト
+
+    #[test]
+    fn spec_without_fallback() {
+        let spec = SelectorSpec::parse("article");
+        assert_eq!(
+            spec.alternatives,
+            vec![Selector::Css("article".to_string())]
+        );
+        assert!(!spec.has_fallback());
+    }
+
+    #[test]
+    fn spec_with_fallback_chain() {
+        let spec = SelectorSpec::parse("article || main || #content");
+        assert_eq!(
+            spec.alternatives,
+            vec![
+                Selector::Css("article".to_string()),
+                Selector::Css("main".to_string()),
+                Selector::Css("#content".to_string()),
+            ],
+        );
+        assert!(spec.has_fallback());
+        assert_eq!(spec.to_string(), "article || main || #content");
+    }
+
+    #[test]
+    fn spec_mixes_css_and_xpath_alternatives() {
+        let spec = SelectorSpec::parse("xpath://main || .content");
+        assert_eq!(
+            spec.alternatives,
+            vec![
+                Selector::XPath("//main".to_string()),
+                Selector::Css(".content".to_string()),
+            ],
+        );
+    }
+
+    #[test]
+    fn spec_ignores_separator_inside_quotes() {
+        let spec = SelectorSpec::parse(r#"a[title="x || y"] || main"#);
+        assert_eq!(
+            spec.alternatives,
+            vec![
+                Selector::Css(r#"a[title="x || y"]"#.to_string()),
+                Selector::Css("main".to_string()),
+            ],
+        );
+    }
+
+    #[test]
+    fn spec_ignores_escaped_quote() {
+        let spec = SelectorSpec::parse(r#"a[title='it\'s || x'] || main"#);
+        assert_eq!(spec.alternatives.len(), 2);
+    }
+
+    #[test]
+    fn spec_skips_empty_alternatives() {
+        let spec = SelectorSpec::parse("article || || main ||");
+        assert_eq!(
+            spec.alternatives,
+            vec![
+                Selector::Css("article".to_string()),
+                Selector::Css("main".to_string()),
+            ],
+        );
+    }
+
+    #[test]
+    fn spec_xpath_alternatives() {
+        let spec = SelectorSpec::parse_xpath("//article || //main[contains(., '||')]");
+        assert_eq!(
+            spec.alternatives,
+            vec![
+                Selector::XPath("//article".to_string()),
+                Selector::XPath("//main[contains(., '||')]".to_string()),
+            ],
+        );
+    }
+
+    // Expectation のテスト
+
+    #[test]
+    fn expectation_parses_selector_and_min() {
+        assert_eq!(
+            "article h2>=3".parse::<Expectation>().unwrap(),
+            Expectation {
+                selector: "article h2".to_string(),
+                min: 3,
+            },
+        );
+    }
+
+    #[test]
+    fn expectation_trims_whitespace() {
+        assert_eq!(
+            "main >= 1".parse::<Expectation>().unwrap(),
+            Expectation {
+                selector: "main".to_string(),
+                min: 1,
+            },
+        );
+    }
+
+    #[test]
+    fn expectation_splits_on_last_operator() {
+        let e = "xpath://li[position()>=2]>=4"
+            .parse::<Expectation>()
+            .unwrap();
+        assert_eq!(e.selector, "xpath://li[position()>=2]");
+        assert_eq!(e.min, 4);
+    }
+
+    #[test]
+    fn expectation_rejects_missing_operator() {
+        assert!("main".parse::<Expectation>().is_err());
+    }
+
+    #[test]
+    fn expectation_rejects_invalid_count() {
+        assert!("main>=many".parse::<Expectation>().is_err());
+        assert!("main>=-1".parse::<Expectation>().is_err());
+    }
+
+    #[test]
+    fn expectation_rejects_missing_selector() {
+        assert!(">=1".parse::<Expectation>().is_err());
+    }
+
+    // check_match_counts のテスト
+
+    fn specs(raw: &[&str]) -> Vec<SelectorSpec> {
+        raw.iter().map(|s| SelectorSpec::parse(s)).collect()
+    }
+
+    fn expect(s: &str) -> Expectation {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn check_passes_without_conditions() {
+        assert_eq!(
+            check_match_counts(&specs(&["main", "aside"]), &[1, 0], false, &[]),
+            Ok(()),
+        );
+    }
+
+    #[test]
+    fn check_strict_fails_on_unmatched_selector() {
+        assert_eq!(
+            check_match_counts(&specs(&["main", "aside"]), &[1, 0], true, &[]),
+            Err(SelectorMismatch {
+                failures: vec!["no elements matched selector 'aside'".to_string()],
+            }),
+        );
+    }
+
+    #[test]
+    fn check_strict_passes_when_all_matched() {
+        assert_eq!(
+            check_match_counts(&specs(&["main", "aside"]), &[1, 2], true, &[]),
+            Ok(()),
+        );
+    }
+
+    #[test]
+    fn check_expectation_minimum() {
+        let specs = specs(&["article h2"]);
+        assert_eq!(
+            check_match_counts(&specs, &[3], false, &[expect("article h2>=3")]),
+            Ok(()),
+        );
+        assert_eq!(
+            check_match_counts(&specs, &[2], false, &[expect("article h2>=3")]),
+            Err(SelectorMismatch {
+                failures: vec![
+                    "selector 'article h2' matched 2 element(s), expected at least 3".to_string(),
+                ],
+            }),
+        );
+    }
+
+    #[test]
+    fn check_reports_all_failures() {
+        let err = check_match_counts(&specs(&["main", "nav"]), &[0, 1], true, &[expect("nav>=2")])
+            .unwrap_err();
+        assert_eq!(err.failures.len(), 2);
+        assert_eq!(
+            err.to_string(),
+            "Selector expectations not met: no elements matched selector 'main'; \
+             selector 'nav' matched 1 element(s), expected at least 2",
+        );
+    }
+
+    #[test]
+    fn find_unknown_expectation_detects_typo() {
+        let specs = specs(&["main", "article || section"]);
+        let expectations = [expect("article || section>=1"), expect("mian>=1")];
+        assert_eq!(
+            find_unknown_expectation(&specs, &expectations),
+            Some(&expectations[1]),
+        );
+        assert_eq!(find_unknown_expectation(&specs, &expectations[..1]), None);
+    }
+}
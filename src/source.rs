@@ -0,0 +1,162 @@
+use anyhow::{Context, Result};
+use headless_chrome::Tab;
+use serde::Deserialize;
+use url::Url;
+
+/// サイトが公開している Markdown 版を探して取得する JavaScript。
+/// `<link rel="alternate" type="text/markdown">` を最優先し、続いて `CANDIDATES` を順に試す。
+const SOURCE_JS: &str = r#"(async () => {
+    const candidates = [];
+    const alternate = document.querySelector(
+        'link[rel~="alternate" i][type="text/markdown" i][href]');
+    if (alternate) candidates.push(alternate.href);
+    candidates.push(...CANDIDATES);
+    for (const url of candidates) {
+        try {
+            const res = await fetch(url, { credentials: 'include' });
+            if (!res.ok) continue;
+            // SPA のフォールバックで HTML が返る場合を除外する
+            // （`<div align="center">` で始まる README などは Markdown として受け入れる）
+            const type = (res.headers.get('content-type') || '').toLowerCase();
+            if (type && !/markdown|text\/plain/.test(type)) continue;
+            const text = await res.text();
+            if (!text.trim() || /^\s*<(?:!doctype|html)[\s>]/i.test(text)) continue;
+            return JSON.stringify({ url: res.url || url, text });
+        } catch (_) {
+            // 取得できない候補は飛ばす
+        }
+    }
+    return null;
+})()"#;
+
+/// 取得した Markdown 版
+#[derive(Debug, Deserialize)]
+pub struct Source {
+    /// 取得先（リダイレクト後）の URL。相対リンクの解決基準になる。
+    pub url: String,
+    pub text: String,
+}
+
+/// ページ URL から推測する Markdown 版の候補（`.md` 版、サイトトップなら `llms.txt`）
+pub fn source_candidates(page_url: &Url) -> Vec<String> {
+    let path = page_url.path();
+    let paths: Vec<String> = if path.is_empty() || path == "/" {
+        vec!["/index.md".to_string(), "/llms.txt".to_string()]
+    } else if let Some(dir) = path.strip_suffix('/') {
+        vec![format!("{dir}.md"), format!("{path}index.md")]
+    } else if let Some(stem) = path.strip_suffix(".html") {
+        vec![format!("{path}.md"), format!("{stem}.md")]
+    } else if path.ends_with(".md") {
+        Vec::new()
+    } else {
+        vec![format!("{path}.md")]
+    };
+    paths
+        .iter()
+        .filter_map(|p| page_url.join(p).ok())
+        .map(String::from)
+        .collect()
+}
+
+/// Markdown 版を探すスクリプトを生成する
+pub fn source_script(candidates: &[String]) -> String {
+    let list: Vec<String> = candidates
+        .iter()
+        .map(|c| crate::escape_js_string(c))
+        .collect();
+    SOURCE_JS.replace("CANDIDATES", &format!("[{}]", list.join(", ")))
+}
+
+/// 読み込み済みのページから、サイトが公開している Markdown 版を取得する
+pub fn fetch_source(tab: &Tab, page_url: &str) -> Result<Option<Source>> {
+    let candidates = Url::parse(page_url)
+        .map(|url| source_candidates(&url))
+        .unwrap_or_default();
+    let result = tab
+        .evaluate(&source_script(&candidates), true)
+        .context("Failed to look for a Markdown source")?;
+    match result.value.as_ref().and_then(|v| v.as_str()) {
+        Some(json) => serde_json::from_str(json)
+            .map(Some)
+            .context("Invalid Markdown source result"),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(url: &str) -> Vec<String> {
+        source_candidates(&Url::parse(url).unwrap())
+    }
+
+    // source_candidates のテスト
+
+    #[test]
+    fn candidates_for_page() {
+        assert_eq!(
+            candidates("https://example.com/docs/intro?x=1#top"),
+            vec!["https://example.com/docs/intro.md"]
+        );
+    }
+
+    #[test]
+    fn candidates_for_directory() {
+        assert_eq!(
+            candidates("https://example.com/docs/"),
+            vec![
+                "https://example.com/docs.md",
+                "https://example.com/docs/index.md"
+            ]
+        );
+    }
+
+    #[test]
+    fn candidates_for_html_page() {
+        assert_eq!(
+            candidates("https://example.com/guide.html"),
+            vec![
+                "https://example.com/guide.html.md",
+                "https://example.com/guide.md"
+            ]
+        );
+    }
+
+    #[test]
+    fn candidates_for_site_root_include_llms_txt() {
+        assert_eq!(
+            candidates("https://example.com"),
+            vec![
+                "https://example.com/index.md",
+                "https://example.com/llms.txt"
+            ]
+        );
+    }
+
+    #[test]
+    fn candidates_for_markdown_url_are_empty() {
+        assert!(candidates("https://example.com/README.md").is_empty());
+    }
+
+    // source_script のテスト
+
+    #[test]
+    fn script_embeds_candidates() {
+        let js = source_script(&["https://example.com/a.md".to_string()]);
+        assert!(js.contains(r#"candidates.push(...["https://example.com/a.md"]);"#));
+        assert!(js.contains(r#"type="text/markdown" i"#));
+    }
+
+    #[test]
+    fn script_rejects_only_html_documents() {
+        let js = source_script(&[]);
+        assert!(js.contains(r"/^\s*<(?:!doctype|html)[\s>]/i.test(text)"));
+    }
+
+    #[test]
+    fn script_without_candidates() {
+        let js = source_script(&[]);
+        assert!(js.contains("candidates.push(...[]);"));
+    }
+}
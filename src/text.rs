@@ -0,0 +1,201 @@
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+/// Markdown の解釈に使う拡張（htmd が出力するテーブル・取り消し線・タスクリスト）
+pub const MARKDOWN_OPTIONS: Options = Options::ENABLE_TABLES
+    .union(Options::ENABLE_STRIKETHROUGH)
+    .union(Options::ENABLE_TASKLISTS);
+
+/// text / html 出力に使う拡張（数式の `$` / `$$` も解釈する）。
+/// `$1 ... $2` のような文章がリンクごと数式になるため、URL の書き換えには使わない。
+pub const MARKDOWN_OPTIONS_WITH_MATH: Options = MARKDOWN_OPTIONS.union(Options::ENABLE_MATH);
+
+/// Markdown の記法を取り除き、段落・リストの行頭記号・テーブルの行を残したテキストにする
+pub fn markdown_to_text(md: &str) -> String {
+    let mut writer = TextWriter::default();
+    for event in Parser::new_ext(md, MARKDOWN_OPTIONS_WITH_MATH) {
+        writer.event(event);
+    }
+    writer.out.trim_end().to_string()
+}
+
+#[derive(Default)]
+struct TextWriter {
+    out: String,
+    /// 入れ子のリスト（番号付きなら次の番号）
+    lists: Vec<Option<u64>>,
+    /// リスト項目の行頭記号を書いた直後か
+    at_item_start: bool,
+    /// 書きかけのテーブル行のセル
+    row: Vec<String>,
+    /// 書きかけのテーブルセル
+    cell: Option<String>,
+}
+
+impl TextWriter {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) | Event::Code(text) | Event::InlineMath(text) => self.push(&text),
+            Event::DisplayMath(text) => self.push(text.trim()),
+            Event::FootnoteReference(label) => self.push(&format!("[{label}]")),
+            Event::TaskListMarker(checked) => self.push(if checked { "[x] " } else { "[ ] " }),
+            Event::SoftBreak => self.push(" "),
+            Event::HardBreak => self.push("\n"),
+            Event::Rule => self.start_block(),
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph
+            | Tag::Heading { .. }
+            | Tag::BlockQuote(_)
+            | Tag::CodeBlock(_)
+            | Tag::Table(_) => self.start_block(),
+            Tag::FootnoteDefinition(label) => {
+                self.start_block();
+                self.push(&format!("[{label}] "));
+            }
+            Tag::List(start) => {
+                self.start_block();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.newline();
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.push(&format!("{indent}{marker}"));
+                self.at_item_start = true;
+            }
+            Tag::TableHead | Tag::TableRow => self.row.clear(),
+            Tag::TableCell => self.cell = Some(String::new()),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::List(_) => {
+                self.lists.pop();
+            }
+            TagEnd::TableCell => {
+                let cell = self.cell.take().unwrap_or_default();
+                self.row.push(cell.trim().to_string());
+            }
+            TagEnd::TableHead | TagEnd::TableRow => {
+                self.newline();
+                let row = self.row.join(" | ");
+                self.push(&row);
+            }
+            _ => {}
+        }
+    }
+
+    fn push(&mut self, text: &str) {
+        match &mut self.cell {
+            Some(cell) => cell.push_str(text),
+            None => self.out.push_str(text),
+        }
+        self.at_item_start = false;
+    }
+
+    fn newline(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    /// ブロックの前で改行する（リストの外では空行を空ける）
+    fn start_block(&mut self) {
+        if self.at_item_start {
+            return;
+        }
+        self.newline();
+        if self.lists.is_empty() && !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // markdown_to_text のテスト
+
+    #[test]
+    fn strips_inline_syntax() {
+        assert_eq!(
+            markdown_to_text(
+                "# Title\n\nSome **bold**, _em_, `code` and [a link](https://example.com).\n\n![Logo](logo.png)"
+            ),
+            "Title\n\nSome bold, em, code and a link.\n\nLogo"
+        );
+    }
+
+    #[test]
+    fn keeps_list_bullets() {
+        assert_eq!(
+            markdown_to_text("Intro\n\n- one\n- two\n  - nested\n\n1. first\n2. second\n\nEnd"),
+            "Intro\n\n- one\n- two\n  - nested\n\n1. first\n2. second\n\nEnd"
+        );
+    }
+
+    #[test]
+    fn keeps_loose_list_items_on_marker_line() {
+        assert_eq!(markdown_to_text("- one\n\n- two\n"), "- one\n- two");
+    }
+
+    #[test]
+    fn keeps_task_list_markers() {
+        assert_eq!(
+            markdown_to_text("- [x] done\n- [ ] todo"),
+            "- [x] done\n- [ ] todo"
+        );
+    }
+
+    #[test]
+    fn keeps_table_rows() {
+        assert_eq!(
+            markdown_to_text("| Name | Value |\n| - | - |\n| a | **1** |\n| b | 2 |"),
+            "Name | Value\na | 1\nb | 2"
+        );
+    }
+
+    #[test]
+    fn keeps_code_block_content() {
+        assert_eq!(
+            markdown_to_text("Run:\n\n```bash\ncargo build\ncargo test\n```\n\nDone"),
+            "Run:\n\ncargo build\ncargo test\n\nDone"
+        );
+    }
+
+    #[test]
+    fn drops_rules_quotes_and_html() {
+        assert_eq!(
+            markdown_to_text("A\n\n---\n\n> quoted\n\n<div>raw</div>\n\nB"),
+            "A\n\nquoted\n\nB"
+        );
+    }
+
+    #[test]
+    fn keeps_line_breaks_within_paragraph() {
+        assert_eq!(markdown_to_text("a\nb  \nc"), "a b\nc");
+    }
+
+    #[test]
+    fn keeps_math_source() {
+        assert_eq!(
+            markdown_to_text("Mass $E = mc^2$ holds.\n\n$$\n\\int_0^1 x\\,dx\n$$\n\nEnd"),
+            "Mass E = mc^2 holds.\n\n\\int_0^1 x\\,dx\n\nEnd"
+        );
+    }
+}
@@ -28,4 +28,9 @@ fn fetch_github_raw_readme() {
         stdout.contains("get-md"),
         "Output should contain 'get-md': got:\n{stdout}",
     );
+    // text/plain の本文は変換されずにそのまま出力される
+    assert!(
+        stdout.contains("```bash"),
+        "Raw Markdown fences should be preserved: got:\n{stdout}",
+    );
 }